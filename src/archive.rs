@@ -0,0 +1,98 @@
+//! Reading audio directly out of ZIP archives, so zipped dataset bundles can
+//! be used as `--input` without a manual unzip step. Entry names double as
+//! the normalized relative paths used for metadata matching, the same role
+//! `recv_dir` entries play for a plain folder scan.
+
+use std::fs::File as StdFile;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// A single non-directory entry found inside a ZIP archive.
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Lists the non-directory entries in `archive_path`.
+pub fn list_entries(archive_path: &Path) -> Result<Vec<ZipEntry>> {
+    let file = StdFile::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        entries.push(ZipEntry {
+            name: entry.name().to_string(),
+            size: entry.size(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads a single named entry's decompressed bytes out of `archive_path`.
+pub fn read_entry(archive_path: &Path, entry_name: &str) -> Result<Vec<u8>> {
+    let file = StdFile::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut entry = archive.by_name(entry_name)?;
+
+    let mut buffer = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// True when `path` looks like a ZIP archive based on its extension.
+pub fn is_zip_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn build_test_zip(path: &Path) {
+        let file = StdFile::create(path).unwrap();
+        let mut writer = zip::ZipWriter::new(file);
+        let options = zip::write::FileOptions::<()>::default();
+
+        writer.start_file("clip.wav", options).unwrap();
+        writer.write_all(b"fake wav bytes").unwrap();
+
+        writer.add_directory("nested/", options).unwrap();
+        writer.start_file("nested/other.wav", options).unwrap();
+        writer.write_all(b"more fake bytes").unwrap();
+
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn list_and_read_entry_round_trip_through_a_zip_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive_path = temp_dir.path().join("bundle.zip");
+        build_test_zip(&archive_path);
+
+        let entries = list_entries(&archive_path).unwrap();
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert!(names.contains(&"clip.wav"));
+        assert!(names.contains(&"nested/other.wav"));
+        assert!(!names.iter().any(|name| name.ends_with('/')));
+
+        let bytes = read_entry(&archive_path, "nested/other.wav").unwrap();
+        assert_eq!(bytes, b"more fake bytes");
+    }
+
+    #[test]
+    fn is_zip_path_matches_case_insensitive_extension() {
+        assert!(is_zip_path(Path::new("bundle.zip")));
+        assert!(is_zip_path(Path::new("bundle.ZIP")));
+        assert!(!is_zip_path(Path::new("clip.wav")));
+    }
+}
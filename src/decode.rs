@@ -0,0 +1,312 @@
+//! Format-agnostic duration/sample-rate probing for the audio bytes read off
+//! disk or an object store. WAV is handled as a fast path via `hound`; every
+//! other container/codec goes through `symphonia`. `hound` doesn't know about
+//! the A-law/μ-law companded formats, so those are detected and decoded by
+//! hand (see [`parse_wav_header`]) ahead of the `hound` fast path.
+
+use std::io::Cursor;
+use std::ops::Range;
+
+use anyhow::{Result, anyhow};
+use hound::WavReader;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+const WAVE_FORMAT_ALAW: u16 = 6;
+const WAVE_FORMAT_MULAW: u16 = 7;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// The handful of `fmt `/`data` chunk fields this module needs, read directly
+/// off the RIFF container since `hound` rejects companded (A-law/μ-law)
+/// format tags outright.
+struct WavHeader {
+    format_tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    data: Range<usize>,
+}
+
+/// Walks a WAV file's RIFF chunks looking for `fmt `/`data`, skipping unknown
+/// or zero-size chunks gracefully (each chunk is sized by its own header, so
+/// one never needs to understand a chunk to skip past it). Resolves
+/// `WAVEFORMATEXTENSIBLE`'s wrapped sub-format tag so companded audio is
+/// still recognized when a writer wraps it in an extensible header. Returns
+/// `None` for anything that isn't a well-formed RIFF/WAVE container.
+fn parse_wav_header(bytes: &[u8]) -> Option<WavHeader> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut format_tag = None;
+    let mut channels = None;
+    let mut sample_rate = None;
+    let mut data_range = None;
+
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let chunk_id = &bytes[pos..pos + 4];
+        let chunk_size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let body_start = pos + 8;
+        let body_end = body_start.checked_add(chunk_size)?.min(bytes.len());
+        let body = &bytes[body_start..body_end];
+
+        if chunk_id == b"fmt " && body.len() >= 16 {
+            let raw_tag = u16::from_le_bytes(body[0..2].try_into().ok()?);
+            format_tag = Some(if raw_tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+                u16::from_le_bytes(body[24..26].try_into().ok()?)
+            } else {
+                raw_tag
+            });
+            channels = Some(u16::from_le_bytes(body[2..4].try_into().ok()?));
+            sample_rate = Some(u32::from_le_bytes(body[4..8].try_into().ok()?));
+        } else if chunk_id == b"data" {
+            data_range = Some(body_start..body_end);
+        }
+
+        // Chunks are padded to an even size; a zero-size chunk still safely
+        // advances past its own 8-byte header.
+        pos = body_start + chunk_size + (chunk_size % 2);
+    }
+
+    Some(WavHeader {
+        format_tag: format_tag?,
+        channels: channels?,
+        sample_rate: sample_rate?,
+        data: data_range?,
+    })
+}
+
+/// Expands one G.711 A-law byte to a 16-bit linear PCM sample.
+fn alaw_to_linear(value: u8) -> i16 {
+    let value = value ^ 0x55;
+    let sign = value & 0x80;
+    let exponent = (value & 0x70) >> 4;
+    let mantissa = (value & 0x0F) as i32;
+
+    let magnitude = if exponent == 0 {
+        (mantissa << 4) + 8
+    } else {
+        ((mantissa << 4) + 0x108) << (exponent - 1)
+    };
+
+    if sign != 0 { magnitude as i16 } else { -(magnitude as i16) }
+}
+
+/// Expands one G.711 μ-law byte to a 16-bit linear PCM sample.
+fn mulaw_to_linear(value: u8) -> i16 {
+    let value = !value;
+    let exponent = (value & 0x70) >> 4;
+    let mantissa = (value & 0x0F) as i32;
+
+    let magnitude = ((mantissa << 3) + 0x84) << exponent;
+
+    if value & 0x80 != 0 {
+        -(magnitude - 0x84) as i16
+    } else {
+        (magnitude - 0x84) as i16
+    }
+}
+
+/// Decodes an A-law/μ-law `data` chunk (one companded byte per sample) to
+/// interleaved `f32` PCM in `[-1.0, 1.0]`.
+fn decode_companded_wav(bytes: &[u8], header: &WavHeader) -> Result<(Vec<f32>, u32, u16)> {
+    let data = bytes
+        .get(header.data.clone())
+        .ok_or_else(|| anyhow!("wav data chunk out of bounds"))?;
+
+    let expand: fn(u8) -> i16 = if header.format_tag == WAVE_FORMAT_ALAW {
+        alaw_to_linear
+    } else {
+        mulaw_to_linear
+    };
+
+    let samples = data
+        .iter()
+        .map(|&byte| expand(byte) as f32 / i16::MAX as f32)
+        .collect();
+
+    Ok((samples, header.sample_rate, header.channels.max(1)))
+}
+
+/// Returns `(duration_seconds, sample_rate_hz)` for the given in-memory audio
+/// file. WAV is probed directly via `hound`; other formats are decoded via
+/// `symphonia`, computing duration from `n_frames / sample_rate` when the
+/// container reports a frame count, or by summing decoded packet durations
+/// otherwise. Returns `(0.0, 0)` when no decoder matches the bytes.
+pub fn duration_and_sample_rate(bytes: &[u8]) -> (f64, i32) {
+    if let Some(header) = parse_wav_header(bytes)
+        && matches!(header.format_tag, WAVE_FORMAT_ALAW | WAVE_FORMAT_MULAW)
+    {
+        let channels = header.channels.max(1) as usize;
+        let frame_count = header.data.len() / channels;
+        return (
+            frame_count as f64 / header.sample_rate as f64,
+            header.sample_rate as i32,
+        );
+    }
+
+    if let Ok(reader) = WavReader::new(Cursor::new(bytes)) {
+        let spec = reader.spec();
+        return (
+            reader.duration() as f64 / spec.sample_rate as f64,
+            spec.sample_rate as i32,
+        );
+    }
+
+    symphonia_duration_and_sample_rate(bytes).unwrap_or((0.0, 0))
+}
+
+fn symphonia_duration_and_sample_rate(bytes: &[u8]) -> Option<(f64, i32)> {
+    let source = Box::new(Cursor::new(bytes.to_vec()));
+    let mss = MediaSourceStream::new(source, Default::default());
+
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &Hint::new(),
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)?
+        .clone();
+
+    let params = &track.codec_params;
+    let sample_rate = params.sample_rate?;
+
+    if let Some(n_frames) = params.n_frames {
+        return Some((n_frames as f64 / sample_rate as f64, sample_rate as i32));
+    }
+
+    // No frame count in the container headers (common for some MP3/OGG
+    // streams): fall back to summing decoded packet durations.
+    let mut decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .ok()?;
+
+    let track_id = track.id;
+    let mut total_frames: u64 = 0;
+
+    loop {
+        let packet = match probed.format.next_packet() {
+            Ok(packet) => packet,
+            Err(_) => break,
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => total_frames += decoded.frames() as u64,
+            Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
+            Err(_) => break,
+        }
+    }
+
+    if total_frames == 0 {
+        return None;
+    }
+
+    Some((total_frames as f64 / sample_rate as f64, sample_rate as i32))
+}
+
+/// Decodes the given in-memory audio file to interleaved `f32` PCM samples in
+/// `[-1.0, 1.0]`, returning `(samples, sample_rate, channels)`. WAV is decoded
+/// directly via `hound`; every other format goes through `symphonia`. Used by
+/// the transcode stage, which needs real sample data rather than just the
+/// duration/rate [`duration_and_sample_rate`] probes.
+pub fn decode_pcm(bytes: &[u8]) -> Result<(Vec<f32>, u32, u16)> {
+    if let Some(header) = parse_wav_header(bytes)
+        && matches!(header.format_tag, WAVE_FORMAT_ALAW | WAVE_FORMAT_MULAW)
+    {
+        return decode_companded_wav(bytes, &header);
+    }
+
+    if let Ok(mut reader) = WavReader::new(Cursor::new(bytes)) {
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .collect::<std::result::Result<Vec<f32>, _>>()?,
+            hound::SampleFormat::Int => {
+                let max_value = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .collect::<std::result::Result<Vec<i32>, _>>()?
+                    .into_iter()
+                    .map(|sample| sample as f32 / max_value)
+                    .collect()
+            }
+        };
+        return Ok((samples, spec.sample_rate, spec.channels));
+    }
+
+    symphonia_decode_pcm(bytes)
+}
+
+fn symphonia_decode_pcm(bytes: &[u8]) -> Result<(Vec<f32>, u32, u16)> {
+    let source = Box::new(Cursor::new(bytes.to_vec()));
+    let mss = MediaSourceStream::new(source, Default::default());
+
+    let mut probed = symphonia::default::get_probe().format(
+        &Hint::new(),
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+        .cloned()
+        .ok_or_else(|| anyhow!("no decodable audio track found"))?;
+
+    let params = &track.codec_params;
+    let sample_rate = params
+        .sample_rate
+        .ok_or_else(|| anyhow!("track reports no sample rate"))?;
+    let channels = params
+        .channels
+        .map(|channels| channels.count() as u16)
+        .unwrap_or(1);
+
+    let mut decoder = symphonia::default::get_codecs().make(params, &DecoderOptions::default())?;
+    let track_id = track.id;
+    let mut samples = Vec::new();
+
+    loop {
+        let packet = match probed.format.next_packet() {
+            Ok(packet) => packet,
+            Err(_) => break,
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                let mut sample_buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                sample_buffer.copy_interleaved_ref(decoded);
+                samples.extend_from_slice(sample_buffer.samples());
+            }
+            Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
+            Err(_) => break,
+        }
+    }
+
+    Ok((samples, sample_rate, channels))
+}
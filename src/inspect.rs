@@ -0,0 +1,424 @@
+//! Read-back integrity/summary report for an already-produced dataset
+//! Parquet shard, exposed via `--inspect`. Complements the writer with a
+//! quick sanity check after a long conversion run, without pulling in
+//! Python/pandas.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File as StdFile;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use polars::prelude::*;
+
+use crate::decode;
+use crate::normalized_relative_path_str;
+use crate::dedup;
+
+/// Opens the Parquet shard at `path` and prints schema, row count, total
+/// audio bytes, per-column null counts, an optional distinct-value
+/// histogram, and an optional row preview. `columns` restricts the schema
+/// and null-count listing to the named columns (all columns when empty).
+/// When `verify_audio` is set, also decodes every `audio.bytes` entry and
+/// reports rows whose `audio.sampling_rate`/`duration` disagree with the
+/// decoded audio; rows from a `--dedup` shard are reassembled from a sibling
+/// `dedup_chunks.parquet` first (see `load_chunk_store`).
+pub fn run(
+    path: &Path,
+    columns: &[String],
+    head: Option<usize>,
+    histogram_column: Option<&str>,
+    verify_audio: bool,
+) -> Result<()> {
+    let mut file = StdFile::open(path).with_context(|| format!("opening {path:?}"))?;
+    let df = ParquetReader::new(&mut file)
+        .finish()
+        .with_context(|| format!("reading parquet shard {path:?}"))?;
+
+    let wanted = |name: &str| columns.is_empty() || columns.iter().any(|c| c == name);
+
+    println!("schema:");
+    for series in df.get_columns() {
+        if wanted(series.name()) {
+            println!("  {}: {:?}", series.name(), series.dtype());
+        }
+    }
+    println!("rows: {}", df.height());
+
+    if let Ok(audio_struct) = df.column("audio").and_then(|c| c.struct_())
+        && let Ok(bytes_field) = audio_struct.field_by_name("bytes")
+        && let Ok(bytes) = bytes_field.binary()
+    {
+        let total_bytes: usize = bytes.iter().filter_map(|b| b.map(|b| b.len())).sum();
+        println!("total audio bytes: {total_bytes}");
+    }
+
+    println!("null counts:");
+    for series in df.get_columns() {
+        if wanted(series.name()) {
+            println!("  {}: {}", series.name(), series.null_count());
+        }
+    }
+
+    if let Some(histogram_column) = histogram_column {
+        print_histogram(&df, histogram_column)?;
+    }
+
+    if let Some(n) = head {
+        println!("{}", df.head(Some(n)));
+    }
+
+    if verify_audio {
+        let chunk_store = load_chunk_store(path);
+        verify_audio_consistency(&df, chunk_store.as_ref())?;
+    }
+
+    Ok(())
+}
+
+/// Loads the sibling `dedup_chunks.parquet` a `--dedup` run writes next to
+/// its shards, if present, keyed by chunk digest, so `verify_audio_consistency`
+/// can reassemble a deduped row's bytes from its `chunk_digests` column before
+/// decoding them. Returns `None` when the file isn't there, e.g. because the
+/// shard wasn't produced with `--dedup`.
+fn load_chunk_store(shard_path: &Path) -> Option<HashMap<String, Vec<u8>>> {
+    let chunk_path = shard_path.parent()?.join("dedup_chunks.parquet");
+    let mut file = StdFile::open(&chunk_path).ok()?;
+    let chunks_df = ParquetReader::new(&mut file).finish().ok()?;
+    let digest_col = chunks_df.column("digest").ok()?.str().ok()?.clone();
+    let bytes_col = chunks_df.column("bytes").ok()?.binary().ok()?.clone();
+
+    let mut store = HashMap::new();
+    for idx in 0..chunks_df.height() {
+        if let (Some(digest), Some(bytes)) = (digest_col.get(idx), bytes_col.get(idx)) {
+            store.insert(digest.to_string(), bytes.to_vec());
+        }
+    }
+    Some(store)
+}
+
+/// Decodes every `audio.bytes` entry and reports rows whose recorded
+/// `audio.sampling_rate`/`duration` disagree with what the bytes actually
+/// decode to (duration may differ by up to 5% of the recorded duration, or by
+/// up to 50ms for clips shorter than a second, to allow for codec block-size
+/// rounding). A row produced by `--dedup` has its bytes reassembled
+/// from `chunk_digests` via `chunk_store` first; if no chunk store was found
+/// alongside the shard, such rows are reported as not directly verifiable
+/// instead of being counted as mismatches. Returns the number of mismatching
+/// rows.
+fn verify_audio_consistency(
+    df: &DataFrame,
+    chunk_store: Option<&HashMap<String, Vec<u8>>>,
+) -> Result<usize> {
+    let audio_struct = df.column("audio")?.struct_()?;
+    let bytes = audio_struct.field_by_name("bytes")?.binary()?.clone();
+    let sampling_rate = audio_struct.field_by_name("sampling_rate")?.i32()?.clone();
+    let path = audio_struct.field_by_name("path")?.str()?.clone();
+    let duration = df.column("duration")?.f64()?.clone();
+    let chunk_digests = df
+        .column("chunk_digests")
+        .ok()
+        .and_then(|c| c.str().ok())
+        .cloned();
+
+    let mut mismatches = 0usize;
+    let mut not_verifiable = 0usize;
+    for idx in 0..df.height() {
+        let Some(clip_bytes) = bytes.get(idx) else {
+            continue;
+        };
+        let relative_path = path
+            .get(idx)
+            .map(normalized_relative_path_str)
+            .unwrap_or_default();
+
+        let reassembled;
+        let clip_bytes = if clip_bytes.is_empty()
+            && let Some(digests_json) = chunk_digests.as_ref().and_then(|col| col.get(idx))
+        {
+            let Some(store) = chunk_store else {
+                println!(
+                    "skipping {relative_path}: --dedup shard with no dedup_chunks.parquet alongside it, not directly verifiable"
+                );
+                not_verifiable += 1;
+                continue;
+            };
+            let digests: Vec<String> = serde_json::from_str(digests_json).unwrap_or_default();
+            reassembled = dedup::reassemble(store, &digests);
+            reassembled.as_slice()
+        } else {
+            clip_bytes
+        };
+
+        let (decoded_duration, decoded_rate) = decode::duration_and_sample_rate(clip_bytes);
+
+        let expected_rate = sampling_rate.get(idx).unwrap_or(0);
+        let expected_duration = duration.get(idx).unwrap_or(0.0);
+
+        let rate_ok = decoded_rate == expected_rate;
+        let duration_ok =
+            (decoded_duration - expected_duration).abs() < 0.05 * expected_duration.max(1.0);
+
+        if !rate_ok || !duration_ok {
+            mismatches += 1;
+            println!(
+                "mismatch at {relative_path}: recorded sampling_rate={expected_rate} duration={expected_duration:.3}, decoded sampling_rate={decoded_rate} duration={decoded_duration:.3}"
+            );
+        }
+    }
+
+    println!(
+        "verified {} rows, {mismatches} mismatches, {not_verifiable} not directly verifiable",
+        df.height()
+    );
+    Ok(mismatches)
+}
+
+fn print_histogram(df: &DataFrame, column_name: &str) -> Result<()> {
+    let column = df
+        .column(column_name)
+        .with_context(|| format!("no such column: {column_name}"))?;
+    let as_strings = column.cast(&DataType::String)?;
+    let str_values = as_strings.str()?;
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for value in str_values.iter() {
+        *counts
+            .entry(value.unwrap_or("<null>").to_string())
+            .or_insert(0) += 1;
+    }
+
+    println!("histogram for {column_name}:");
+    for (value, count) in &counts {
+        println!("  {value}: {count}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashMap};
+    use std::io::Cursor;
+
+    use hound::{SampleFormat, WavSpec, WavWriter};
+
+    use crate::{Audio, File, MetadataType, ParquetCompressionChoice, write_files_to_parquet};
+
+    fn write_sample_shard(path: &Path) {
+        let mut metadata = HashMap::new();
+        metadata.insert("speaker".to_string(), serde_json::json!("alice"));
+
+        let mut metadata_types = HashMap::new();
+        metadata_types.insert("speaker".to_string(), MetadataType::String);
+        let metadata_keys = BTreeSet::from(["speaker".to_string()]);
+
+        let files = vec![File {
+            duration: 1.0,
+            audio: Audio {
+                path: "clip.wav".to_string(),
+                sampling_rate: 16_000,
+                bytes: vec![0_u8, 1, 2, 3],
+                chunk_digests: None,
+                chunk_total_len: None,
+            },
+            metadata,
+        }];
+
+        write_files_to_parquet(
+            path,
+            &files,
+            &metadata_keys,
+            &metadata_types,
+            ParquetCompressionChoice::Snappy,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn run_reports_schema_row_count_and_histogram_without_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shard_path = temp_dir.path().join("sample.parquet");
+        write_sample_shard(&shard_path);
+
+        run(&shard_path, &[], Some(1), Some("speaker"), false).unwrap();
+    }
+
+    #[test]
+    fn print_histogram_counts_distinct_values() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shard_path = temp_dir.path().join("sample.parquet");
+        write_sample_shard(&shard_path);
+
+        let mut file = StdFile::open(&shard_path).unwrap();
+        let df = ParquetReader::new(&mut file).finish().unwrap();
+
+        print_histogram(&df, "speaker").unwrap();
+    }
+
+    fn tone_wav(sample_rate: u32, seconds: f64) -> Vec<u8> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        let frame_count = (sample_rate as f64 * seconds) as usize;
+        let mut bytes = Vec::new();
+        {
+            let mut writer = WavWriter::new(Cursor::new(&mut bytes), spec).unwrap();
+            for i in 0..frame_count {
+                let sample =
+                    (2.0 * std::f64::consts::PI * 440.0 * i as f64 / sample_rate as f64).sin();
+                writer
+                    .write_sample((sample * i16::MAX as f64) as i16)
+                    .unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        bytes
+    }
+
+    fn write_shard_with_audio(path: &Path, audio_bytes: Vec<u8>, sampling_rate: i32, duration: f64) {
+        let metadata_types = HashMap::new();
+        let metadata_keys = BTreeSet::new();
+
+        let files = vec![File {
+            duration,
+            audio: Audio {
+                path: "clip.wav".to_string(),
+                sampling_rate,
+                bytes: audio_bytes,
+                chunk_digests: None,
+                chunk_total_len: None,
+            },
+            metadata: HashMap::new(),
+        }];
+
+        write_files_to_parquet(
+            path,
+            &files,
+            &metadata_keys,
+            &metadata_types,
+            ParquetCompressionChoice::Snappy,
+        )
+        .unwrap();
+    }
+
+    fn read_shard(path: &Path) -> DataFrame {
+        let mut file = StdFile::open(path).unwrap();
+        ParquetReader::new(&mut file).finish().unwrap()
+    }
+
+    #[test]
+    fn verify_audio_consistency_reports_no_mismatches_for_consistent_audio() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shard_path = temp_dir.path().join("sample.parquet");
+        let wav = tone_wav(8_000, 0.5);
+        let (duration, sampling_rate) = decode::duration_and_sample_rate(&wav);
+        write_shard_with_audio(&shard_path, wav, sampling_rate, duration);
+
+        let df = read_shard(&shard_path);
+        assert_eq!(verify_audio_consistency(&df, None).unwrap(), 0);
+
+        run(&shard_path, &[], None, None, true).unwrap();
+    }
+
+    #[test]
+    fn verify_audio_consistency_reports_a_mismatch_for_a_wrong_sampling_rate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shard_path = temp_dir.path().join("sample.parquet");
+        let wav = tone_wav(8_000, 0.5);
+        let (duration, sampling_rate) = decode::duration_and_sample_rate(&wav);
+        write_shard_with_audio(&shard_path, wav, sampling_rate * 2, duration);
+
+        let df = read_shard(&shard_path);
+        assert_eq!(verify_audio_consistency(&df, None).unwrap(), 1);
+    }
+
+    fn write_deduped_shard(
+        path: &Path,
+        sampling_rate: i32,
+        duration: f64,
+        chunk_digests: Vec<String>,
+        chunk_total_len: u64,
+    ) {
+        let metadata_types = HashMap::new();
+        let metadata_keys = BTreeSet::new();
+
+        let files = vec![File {
+            duration,
+            audio: Audio {
+                path: "clip.wav".to_string(),
+                sampling_rate,
+                bytes: Vec::new(),
+                chunk_digests: Some(chunk_digests),
+                chunk_total_len: Some(chunk_total_len),
+            },
+            metadata: HashMap::new(),
+        }];
+
+        write_files_to_parquet(
+            path,
+            &files,
+            &metadata_keys,
+            &metadata_types,
+            ParquetCompressionChoice::Snappy,
+        )
+        .unwrap();
+    }
+
+    fn write_dedup_chunks_table(path: &Path, chunks: &[(&str, &[u8])]) {
+        let digest_series = Series::new(
+            "digest".into(),
+            chunks.iter().map(|(digest, _)| digest.to_string()).collect::<Vec<_>>(),
+        );
+        let bytes_series = Series::new(
+            "bytes".into(),
+            chunks.iter().map(|(_, bytes)| bytes.to_vec()).collect::<Vec<_>>(),
+        );
+        let mut df =
+            DataFrame::new(vec![digest_series.into_column(), bytes_series.into_column()]).unwrap();
+
+        let mut file = StdFile::create(path).unwrap();
+        ParquetWriter::new(&mut file).finish(&mut df).unwrap();
+    }
+
+    #[test]
+    fn verify_audio_consistency_reassembles_deduped_rows_from_the_chunk_store() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shard_path = temp_dir.path().join("sample.parquet");
+        let wav = tone_wav(8_000, 0.5);
+        let (duration, sampling_rate) = decode::duration_and_sample_rate(&wav);
+
+        let mid = wav.len() / 2;
+        write_deduped_shard(
+            &shard_path,
+            sampling_rate,
+            duration,
+            vec!["chunk-a".to_string(), "chunk-b".to_string()],
+            wav.len() as u64,
+        );
+        write_dedup_chunks_table(
+            &temp_dir.path().join("dedup_chunks.parquet"),
+            &[("chunk-a", &wav[..mid]), ("chunk-b", &wav[mid..])],
+        );
+
+        let df = read_shard(&shard_path);
+        let store = load_chunk_store(&shard_path).unwrap();
+        assert_eq!(verify_audio_consistency(&df, Some(&store)).unwrap(), 0);
+
+        run(&shard_path, &[], None, None, true).unwrap();
+    }
+
+    #[test]
+    fn verify_audio_consistency_reports_deduped_rows_as_not_verifiable_without_a_chunk_store() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let shard_path = temp_dir.path().join("sample.parquet");
+        write_deduped_shard(&shard_path, 8_000, 0.5, vec!["chunk-a".to_string()], 4);
+
+        let df = read_shard(&shard_path);
+        assert_eq!(verify_audio_consistency(&df, None).unwrap(), 0);
+    }
+}
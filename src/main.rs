@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File as StdFile;
 use std::fs::create_dir_all;
 use std::path::Path;
@@ -13,18 +13,36 @@ use anyhow::Result;
 use clap::{Parser, ValueEnum};
 use duckdb::types::Value as DuckValue;
 use duckdb::{Connection, params_from_iter};
-use hound::WavReader;
 use polars::prelude::*;
 use rayon::prelude::*;
 use recv_dir::{Filter, MaxDepth, NoSymlink, RecursiveDirIterator};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+mod archive;
+mod decode;
+mod storage;
+mod dedup;
+mod features;
+mod inspect;
+mod loudness;
+mod manifest;
+mod tags;
+mod transcode;
+use storage::Location;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct Audio {
     path: String,
     sampling_rate: i32,
     bytes: Vec<u8>,
+    /// Ordered BLAKE3 digests of this file's content-defined chunks, set
+    /// instead of `bytes` when `--dedup` is enabled; the unique chunk bodies
+    /// live in the side chunk store rather than being repeated per file.
+    chunk_digests: Option<Vec<String>>,
+    /// Total byte length of the reassembled file, set alongside
+    /// `chunk_digests` since `bytes` is left empty in that mode.
+    chunk_total_len: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,12 +70,29 @@ enum ParquetCompressionChoice {
     Lz4Raw,
 }
 
+/// The output container for the (possibly resampled/downmixed) audio bytes.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TargetCodec {
+    /// Keep the source encoding, unless resampling/downmixing forces a
+    /// re-encode, in which case this falls back to PCM WAV.
+    PassThrough,
+    /// 16-bit PCM WAV.
+    Wav,
+    Flac,
+    /// Constant-quality MP3, for corpora where storage size matters more than
+    /// bit-exact reproduction.
+    Mp3,
+}
+
 #[derive(Parser, Debug)]
 #[command(version, long_about = None)]
 struct Args {
-    /// The path to the input folder (by default, the program will scan the entire folder recursively)
+    /// The input location (by default, the program will scan the entire folder
+    /// recursively). Accepts a local path or a `s3://`/`gs://`/`az://`/`file://`
+    /// URL to scan an object store bucket instead. Required unless `--inspect`
+    /// is set.
     #[arg(long)]
-    input: PathBuf,
+    input: Option<String>,
 
     /// The format of the output database files
     #[arg(long)]
@@ -80,18 +115,187 @@ struct Args {
     #[arg(long, default_value_t = 5)]
     num_threads: usize,
 
-    /// The path to the output files
+    /// The output location. Accepts a local path or a
+    /// `s3://`/`gs://`/`az://`/`file://` URL to upload shards straight to a
+    /// bucket instead of staging them on local disk. Required unless
+    /// `--inspect` is set.
     #[arg(long)]
-    output: PathBuf,
+    output: Option<String>,
 
     /// The compression algorithm to use for Parquet files
     #[arg(long)]
     #[clap(value_enum, default_value_t = ParquetCompressionChoice::Snappy)]
     parquet_compression: ParquetCompressionChoice,
 
-    /// Metadata file (CSV or JSONL) describing per-file fields
+    /// Metadata file (CSV, TSV, or JSONL) describing per-file fields
     #[arg(long)]
     metadata_file: Option<PathBuf>,
+
+    /// Read embedded tags (ID3v2, Vorbis comments, MP4 atoms, ...) from each
+    /// audio file and merge them into its metadata columns. An explicit
+    /// `--metadata-file` value wins on key conflicts.
+    #[arg(long, default_value_t = false)]
+    read_tags: bool,
+
+    /// Split each file into content-defined chunks and store unique chunks
+    /// once instead of repeating identical audio bytes across rows.
+    #[arg(long, default_value_t = false)]
+    dedup: bool,
+
+    /// Minimum content-defined chunk size in bytes, used when `--dedup` is
+    /// set. Must be greater than 0 and no larger than `--dedup-max-size`,
+    /// or startup fails with an error
+    #[arg(long, default_value_t = 4096)]
+    dedup_min_size: usize,
+
+    /// Target average content-defined chunk size in bytes, used when
+    /// `--dedup` is set
+    #[arg(long, default_value_t = 16384)]
+    dedup_avg_size: usize,
+
+    /// Maximum content-defined chunk size in bytes, used when `--dedup` is set
+    #[arg(long, default_value_t = 65536)]
+    dedup_max_size: usize,
+
+    /// Bypass the manifest cache in `--output`: re-run the
+    /// duration/sample-rate probe for every file even when its size and
+    /// modification time are unchanged, and re-read/rewrite every shard even
+    /// when every file it would contain is unchanged (see `shard_is_unchanged`
+    /// and `manifest`'s module docs for what the cache skips on its own)
+    #[arg(long, default_value_t = false)]
+    force: bool,
+
+    /// Skip files smaller than this many bytes
+    #[arg(long)]
+    min_size: Option<u64>,
+
+    /// Skip files larger than this many bytes
+    #[arg(long)]
+    max_size: Option<u64>,
+
+    /// Skip files shorter than this many seconds (evaluated after decoding)
+    #[arg(long)]
+    min_duration: Option<f64>,
+
+    /// Skip files longer than this many seconds (evaluated after decoding)
+    #[arg(long)]
+    max_duration: Option<f64>,
+
+    /// Glob pattern(s) matched against the normalized relative path; matching
+    /// files are skipped. May be passed multiple times
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Glob pattern(s) matched against the normalized relative path; when set,
+    /// only matching files are kept. May be passed multiple times
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Follow symlinks instead of skipping them while scanning the input
+    /// folder
+    #[arg(long, default_value_t = false)]
+    deref_symlinks: bool,
+
+    /// Resample every clip to this rate (Hz) before writing it out
+    #[arg(long)]
+    target_sample_rate: Option<u32>,
+
+    /// Downsample clips whose native rate exceeds this value (Hz) to exactly
+    /// this rate; clips already at or below it are left untouched
+    #[arg(long)]
+    max_sample_rate: Option<u32>,
+
+    /// Downmix every clip to this many channels (only `1`, mono, is
+    /// supported today; any other value is rejected at startup) before
+    /// writing it out
+    #[arg(long)]
+    target_channels: Option<u16>,
+
+    /// Shorthand for `--target-channels 1`, matching the mono convention most
+    /// ASR datasets expect
+    #[arg(long, default_value_t = false)]
+    mono: bool,
+
+    /// Output container for the (possibly resampled/downmixed) audio bytes
+    #[clap(long, value_enum, default_value_t = TargetCodec::PassThrough)]
+    target_codec: TargetCodec,
+
+    /// Decode every clip and store canonical PCM WAV bytes instead of the
+    /// original (possibly compressed) file bytes, so `audio.bytes` and
+    /// `duration` always reflect decoded frame counts rather than container
+    /// headers. Ignored when `--target-codec` already requests a specific
+    /// codec
+    #[arg(long, default_value_t = false)]
+    store_decoded_pcm: bool,
+
+    /// Compute a fixed-length tempo/loudness/timbre descriptor per clip and
+    /// store it in the `audio_features` column
+    #[arg(long, default_value_t = false)]
+    extract_features: bool,
+
+    /// Compute RMS energy, zero-crossing rate, and spectral centroid per clip
+    /// and store them as separate `f64` columns alongside `duration`
+    #[arg(long, default_value_t = false)]
+    features: bool,
+
+    /// Measure integrated loudness (EBU R128 / LUFS) per clip and store it
+    /// in the `lufs` column
+    #[arg(long, default_value_t = false)]
+    loudness: bool,
+
+    /// Gain-adjust every clip's decoded PCM to this target integrated
+    /// loudness (LUFS) before writing it out. Implies `--loudness`-style
+    /// measurement internally even if `--loudness` itself is not set. Forces
+    /// a re-encode of the gain-adjusted samples, so unless `--target-codec`
+    /// is also given, `audio.bytes` switches from the clip's original
+    /// container (e.g. MP3) to PCM WAV, the same fallback `--store-decoded-pcm`
+    /// uses
+    #[arg(long)]
+    normalize_lufs: Option<f64>,
+
+    /// Write a Hive-style partitioned layout, one directory per distinct
+    /// value of this comma-separated list of metadata columns (e.g.
+    /// `speaker` or `speaker,language`). Parquet-only: combining this with
+    /// `--format duckdb` is a startup error, since DuckDB output is always a
+    /// single flat file
+    #[arg(long, value_delimiter = ',')]
+    partition_by: Vec<String>,
+
+    /// Split each partition across multiple part files of at most this many
+    /// rows; unset writes one part file per partition per shard
+    #[arg(long)]
+    max_rows_per_file: Option<usize>,
+
+    /// Keep partition columns in the row payload instead of dropping them
+    /// (their values are already encoded in the partition path)
+    #[arg(long, default_value_t = false)]
+    retain_partition_columns: bool,
+
+    /// Instead of building a dataset, open an already-produced Parquet shard
+    /// at this path and print an integrity/summary report, then exit. Ignores
+    /// `--input`/`--output`.
+    #[arg(long)]
+    inspect: Option<PathBuf>,
+
+    /// Limit the inspection report's schema and null-count listing to this
+    /// comma-separated list of columns (default: all); `--histogram-column`
+    /// is reported regardless
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Print this many rows of a preview table when inspecting a dataset
+    #[arg(long)]
+    head: Option<usize>,
+
+    /// Print a distinct-value histogram for this metadata column when
+    /// inspecting a dataset
+    #[arg(long)]
+    histogram_column: Option<String>,
+
+    /// While inspecting, decode every `audio.bytes` entry and flag rows whose
+    /// `audio.sampling_rate`/`duration` disagree with the decoded audio
+    #[arg(long, default_value_t = false)]
+    verify_audio: bool,
 }
 
 const AUDIO_MIME_TYPES: [&str; 12] = [
@@ -114,7 +318,7 @@ fn normalized_relative_path(path: &Path) -> String {
     normalized.trim_start_matches("./").to_string()
 }
 
-fn normalized_relative_path_str(value: &str) -> String {
+pub(crate) fn normalized_relative_path_str(value: &str) -> String {
     value
         .replace('\\', "/")
         .trim_start_matches("./")
@@ -126,6 +330,9 @@ enum MetadataType {
     String,
     Bool,
     Float64,
+    /// A fixed- or variable-length list of floats, round-tripping as a
+    /// Parquet `List<f64>` column (e.g. the `--extract-features` descriptor).
+    FloatArray,
 }
 
 impl MetadataType {
@@ -160,6 +367,29 @@ impl MetadataStore {
             .or_insert(MetadataType::String);
     }
 
+    fn ensure_feature_key(&mut self) {
+        self.keys.insert("audio_features".to_string());
+        self.types
+            .entry("audio_features".to_string())
+            .or_insert(MetadataType::FloatArray);
+    }
+
+    fn ensure_basic_feature_keys(&mut self) {
+        for key in ["rms", "zero_crossing_rate", "spectral_centroid"] {
+            self.keys.insert(key.to_string());
+            self.types
+                .entry(key.to_string())
+                .or_insert(MetadataType::Float64);
+        }
+    }
+
+    fn ensure_loudness_key(&mut self) {
+        self.keys.insert("lufs".to_string());
+        self.types
+            .entry("lufs".to_string())
+            .or_insert(MetadataType::Float64);
+    }
+
     fn update_types_from_record(&mut self, metadata: &HashMap<String, Value>) {
         for (key, value) in metadata {
             self.keys.insert(key.clone());
@@ -210,21 +440,34 @@ fn infer_metadata_type(value: &Value) -> Option<MetadataType> {
         Value::Bool(_) => Some(MetadataType::Bool),
         Value::Number(_) => Some(MetadataType::Float64),
         Value::String(_) => Some(MetadataType::String),
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_number) => {
+            Some(MetadataType::FloatArray)
+        }
         Value::Null => None,
         _ => Some(MetadataType::String),
     }
 }
 
+/// Folds `tags` (e.g. embedded ID3v2/Vorbis/MP4 fields from `--read-tags`)
+/// into `metadata` without overwriting keys already present, so an explicit
+/// `--metadata-file` value always wins over a file's embedded tags.
+fn apply_tag_fallback(metadata: &mut HashMap<String, Value>, tags: &HashMap<String, Value>) {
+    for (key, value) in tags {
+        metadata.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+}
+
 fn sanitize_column_name(name: &str) -> String {
     name.replace('"', "\"\"")
 }
 
-fn is_reserved_metadata_key(key: &str) -> bool {
+pub(crate) fn is_reserved_metadata_key(key: &str) -> bool {
     matches!(key, "duration" | "audio" | "id")
 }
 
 enum MetadataFormat {
     Csv,
+    Tsv,
     Jsonl,
 }
 
@@ -237,19 +480,39 @@ fn metadata_format_from_path(path: &Path) -> MetadataFormat {
 
     match extension.as_str() {
         "jsonl" | "json" => MetadataFormat::Jsonl,
+        "tsv" => MetadataFormat::Tsv,
         _ => MetadataFormat::Csv,
     }
 }
 
 fn load_metadata_store(path: &Path) -> Result<MetadataStore> {
     match metadata_format_from_path(path) {
-        MetadataFormat::Csv => load_csv_metadata(path),
+        MetadataFormat::Csv => load_delimited_metadata(path, b','),
+        MetadataFormat::Tsv => load_delimited_metadata(path, b'\t'),
         MetadataFormat::Jsonl => load_jsonl_metadata(path),
     }
 }
 
-fn load_csv_metadata(path: &Path) -> Result<MetadataStore> {
-    let mut reader = csv::Reader::from_path(path)?;
+/// Parses a CSV/TSV cell into a typed `Value` (bool, then number, falling
+/// back to string) so numeric/boolean sidecar columns surface as their own
+/// `MetadataType` instead of always being treated as strings.
+fn parse_tabular_value(raw: &str) -> Value {
+    if let Ok(value) = raw.parse::<bool>() {
+        return Value::Bool(value);
+    }
+    if let Ok(number) = raw.parse::<f64>()
+        && let Some(number) = serde_json::Number::from_f64(number)
+    {
+        return Value::Number(number);
+    }
+
+    Value::String(raw.to_string())
+}
+
+fn load_delimited_metadata(path: &Path, delimiter: u8) -> Result<MetadataStore> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)?;
     let headers = reader.headers()?.clone();
     let mut store = MetadataStore::new();
 
@@ -273,7 +536,7 @@ fn load_csv_metadata(path: &Path) -> Result<MetadataStore> {
                 }
                 _ => {
                     if !value.is_empty() && !is_reserved_metadata_key(header) {
-                        metadata.insert(header.to_string(), Value::String(value.to_string()));
+                        metadata.insert(header.to_string(), parse_tabular_value(value));
                     }
                 }
             }
@@ -353,6 +616,7 @@ fn load_jsonl_metadata(path: &Path) -> Result<MetadataStore> {
 fn build_create_table_sql(
     metadata_keys: &BTreeSet<String>,
     metadata_types: &HashMap<String, MetadataType>,
+    dedup: bool,
 ) -> String {
     let mut columns = vec![
         "id INTEGER PRIMARY KEY DEFAULT NEXTVAL('seq')".to_string(),
@@ -360,6 +624,11 @@ fn build_create_table_sql(
         "audio STRUCT(path VARCHAR, sampling_rate INTEGER, bytes BLOB)".to_string(),
     ];
 
+    if dedup {
+        columns.push("chunk_digests VARCHAR".to_string());
+        columns.push("chunk_total_len BIGINT".to_string());
+    }
+
     for key in metadata_keys {
         let column_type = metadata_types
             .get(key)
@@ -369,6 +638,7 @@ fn build_create_table_sql(
             MetadataType::Bool => "BOOLEAN",
             MetadataType::Float64 => "DOUBLE",
             MetadataType::String => "VARCHAR",
+            MetadataType::FloatArray => "DOUBLE[]",
         };
 
         columns.push(format!("\"{}\" {}", sanitize_column_name(key), sql_type));
@@ -380,17 +650,25 @@ fn build_create_table_sql(
     )
 }
 
-fn build_insert_sql(metadata_keys: &BTreeSet<String>) -> String {
+fn build_insert_sql(metadata_keys: &BTreeSet<String>, dedup: bool) -> String {
     let mut column_names = vec![
         "id".to_string(),
         "duration".to_string(),
         "audio".to_string(),
     ];
+    if dedup {
+        column_names.push("chunk_digests".to_string());
+        column_names.push("chunk_total_len".to_string());
+    }
     for key in metadata_keys {
         column_names.push(format!("\"{}\"", sanitize_column_name(key)));
     }
 
     let mut placeholders = vec!["?".to_string(), "?".to_string(), "row(?, ?, ?)".to_string()];
+    if dedup {
+        placeholders.push("?".to_string());
+        placeholders.push("?".to_string());
+    }
     placeholders.extend(std::iter::repeat_n("?".to_string(), metadata_keys.len()));
 
     format!(
@@ -400,13 +678,14 @@ fn build_insert_sql(metadata_keys: &BTreeSet<String>) -> String {
     )
 }
 
-fn write_files_to_parquet<P: AsRef<Path>>(
-    output_path: P,
+/// Builds the `DataFrame` plus its Parquet compression/key-value metadata
+/// that both [`write_files_to_parquet`] and [`encode_parquet_bytes`] serialize.
+fn build_output_dataframe(
     files: &[File],
     metadata_keys: &std::collections::BTreeSet<String>,
     metadata_types: &HashMap<String, MetadataType>,
     compression: ParquetCompressionChoice,
-) -> Result<()> {
+) -> Result<(DataFrame, ParquetCompression, KeyValueMetadata)> {
     let duration_data: Vec<Option<f64>> = files.iter().map(|file| Some(file.duration)).collect();
 
     let bytes_data: Vec<Option<Vec<u8>>> = files
@@ -440,6 +719,25 @@ fn write_files_to_parquet<P: AsRef<Path>>(
         duration_series.into_column(),
     ];
 
+    if files.iter().any(|file| file.audio.chunk_digests.is_some()) {
+        let digests_data: Vec<Option<String>> = files
+            .iter()
+            .map(|file| {
+                file.audio
+                    .chunk_digests
+                    .as_ref()
+                    .map(|digests| serde_json::to_string(digests).unwrap_or_default())
+            })
+            .collect();
+        let total_len_data: Vec<Option<i64>> = files
+            .iter()
+            .map(|file| file.audio.chunk_total_len.map(|len| len as i64))
+            .collect();
+
+        columns.push(Series::new("chunk_digests".into(), digests_data).into_column());
+        columns.push(Series::new("chunk_total_len".into(), total_len_data).into_column());
+    }
+
     for key in metadata_keys {
         let column_type = metadata_types
             .get(key)
@@ -473,10 +771,21 @@ fn write_files_to_parquet<P: AsRef<Path>>(
                     .collect();
                 columns.push(Series::new(key.as_str().into(), data).into_column());
             }
+            MetadataType::FloatArray => {
+                let data: Vec<Option<Vec<f64>>> = files
+                    .iter()
+                    .map(|file| {
+                        file.metadata.get(key).and_then(|v| v.as_array()).map(|items| {
+                            items.iter().filter_map(|item| item.as_f64()).collect()
+                        })
+                    })
+                    .collect();
+                columns.push(Series::new(key.as_str().into(), data).into_column());
+            }
         }
     }
 
-    let mut df = DataFrame::new(columns)?;
+    let df = DataFrame::new(columns)?;
 
     let pq_compression = match compression {
         ParquetCompressionChoice::Uncompressed => ParquetCompression::Uncompressed,
@@ -497,20 +806,21 @@ fn write_files_to_parquet<P: AsRef<Path>>(
     );
 
     for key in metadata_keys {
-        let dtype = match metadata_types
+        let feature_json = match metadata_types
             .get(key)
             .copied()
             .unwrap_or(MetadataType::String)
         {
-            MetadataType::Bool => "bool",
-            MetadataType::Float64 => "float64",
-            MetadataType::String => "string",
+            MetadataType::Bool => serde_json::json!({"dtype": "bool", "_type": "Value"}),
+            MetadataType::Float64 => serde_json::json!({"dtype": "float64", "_type": "Value"}),
+            MetadataType::String => serde_json::json!({"dtype": "string", "_type": "Value"}),
+            MetadataType::FloatArray => serde_json::json!({
+                "feature": {"dtype": "float64", "_type": "Value"},
+                "_type": "Sequence"
+            }),
         };
 
-        features.insert(
-            key.clone(),
-            serde_json::json!({"dtype": dtype, "_type": "Value"}),
-        );
+        features.insert(key.clone(), feature_json);
     }
 
     let hf_value = serde_json::json!({"info": {"features": features}});
@@ -518,6 +828,19 @@ fn write_files_to_parquet<P: AsRef<Path>>(
     let custom_metadata =
         KeyValueMetadata::from_static(vec![("huggingface".to_string(), hf_value.to_string())]);
 
+    Ok((df, pq_compression, custom_metadata))
+}
+
+fn write_files_to_parquet<P: AsRef<Path>>(
+    output_path: P,
+    files: &[File],
+    metadata_keys: &std::collections::BTreeSet<String>,
+    metadata_types: &HashMap<String, MetadataType>,
+    compression: ParquetCompressionChoice,
+) -> Result<()> {
+    let (mut df, pq_compression, custom_metadata) =
+        build_output_dataframe(files, metadata_keys, metadata_types, compression)?;
+
     let mut file = StdFile::create(output_path)?;
     ParquetWriter::new(&mut file)
         .with_key_value_metadata(Some(custom_metadata))
@@ -530,45 +853,504 @@ fn write_files_to_parquet<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Same encoding as [`write_files_to_parquet`], but returns the finished
+/// Parquet bytes instead of writing them to a local file, so callers can
+/// `put` them to an object store shard instead.
+fn encode_parquet_bytes(
+    files: &[File],
+    metadata_keys: &std::collections::BTreeSet<String>,
+    metadata_types: &HashMap<String, MetadataType>,
+    compression: ParquetCompressionChoice,
+) -> Result<Vec<u8>> {
+    let (mut df, pq_compression, custom_metadata) =
+        build_output_dataframe(files, metadata_keys, metadata_types, compression)?;
+
+    let mut buffer = Vec::new();
+    ParquetWriter::new(&mut buffer)
+        .with_key_value_metadata(Some(custom_metadata))
+        .with_compression(pq_compression)
+        .with_row_group_size(Some(256))
+        .finish(&mut df)?;
+
+    println!("Successfully encoded {} records to Parquet.", files.len());
+
+    Ok(buffer)
+}
+
+/// Replaces the path separators `/` and `\` in a single partition value with
+/// `_`, and maps a value that is empty, `.` or `..` to `_unknown`, so a
+/// metadata value like `"../../etc"` or `"a/b"` can't escape the partition
+/// directory or introduce extra directory levels.
+fn sanitize_partition_value(value: &str) -> String {
+    let sanitized: String = value
+        .chars()
+        .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+        .collect();
+    match sanitized.as_str() {
+        "" | "." | ".." => "_unknown".to_string(),
+        _ => sanitized,
+    }
+}
+
+/// Builds the Hive-style directory segment for `file` given the ordered
+/// partition columns, e.g. `speaker=alice/language=en`. Missing or null
+/// values fall back to `_unknown` so every file still lands somewhere; other
+/// non-string values use their JSON text. Values are sanitized so a metadata value can never inject a
+/// `/` or `..` path-traversal component.
+fn partition_path(file: &File, partition_by: &[String]) -> String {
+    partition_by
+        .iter()
+        .map(|key| {
+            let value = file
+                .metadata
+                .get(key)
+                .map(|value| match value {
+                    Value::String(s) => s.clone(),
+                    Value::Null => "_unknown".to_string(),
+                    other => other.to_string(),
+                })
+                .unwrap_or_else(|| "_unknown".to_string());
+            format!("{key}={}", sanitize_partition_value(&value))
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Writes `files` as a Hive-style partitioned dataset: one directory per
+/// distinct combination of `partition_by` metadata values, split into
+/// `max_rows_per_file`-sized part files named `part-{shard_idx}-{n}.parquet`.
+/// Partition columns are dropped from the row payload unless
+/// `retain_partition_columns` is set, since their values are already encoded
+/// in the directory path.
+#[allow(clippy::too_many_arguments)]
+fn write_partitioned_parquet(
+    output_location: &Location,
+    shard_idx: usize,
+    files: &[File],
+    partition_by: &[String],
+    max_rows_per_file: Option<usize>,
+    retain_partition_columns: bool,
+    metadata_keys: &BTreeSet<String>,
+    metadata_types: &HashMap<String, MetadataType>,
+    compression: ParquetCompressionChoice,
+    rt: &tokio::runtime::Handle,
+) {
+    let row_metadata_keys: BTreeSet<String> = if retain_partition_columns {
+        metadata_keys.clone()
+    } else {
+        metadata_keys
+            .iter()
+            .filter(|key| !partition_by.contains(key))
+            .cloned()
+            .collect()
+    };
+
+    let mut groups: BTreeMap<String, Vec<File>> = BTreeMap::new();
+    for file in files {
+        let mut row = file.clone();
+        if !retain_partition_columns {
+            for key in partition_by {
+                row.metadata.remove(key);
+            }
+        }
+        groups
+            .entry(partition_path(file, partition_by))
+            .or_default()
+            .push(row);
+    }
+
+    for (partition, rows) in groups {
+        let chunk_size = max_rows_per_file.filter(|n| *n > 0).unwrap_or(rows.len().max(1));
+
+        for (part_idx, part_rows) in rows.chunks(chunk_size).enumerate() {
+            let relative_path = format!("{partition}/part-{shard_idx}-{part_idx}.parquet");
+
+            match output_location.as_local() {
+                Some(output_dir) => {
+                    let full_path = output_dir.join(&relative_path);
+                    if !full_path.starts_with(output_dir) {
+                        eprintln!(
+                            "Refusing to write partition {relative_path}: escapes output directory"
+                        );
+                        continue;
+                    }
+                    if let Some(parent) = full_path.parent() {
+                        let _ = create_dir_all(parent);
+                    }
+                    if let Err(err) = write_files_to_parquet(
+                        &full_path,
+                        part_rows,
+                        &row_metadata_keys,
+                        metadata_types,
+                        compression,
+                    ) {
+                        eprintln!("Failed to write partition {relative_path}: {err}");
+                    }
+                }
+                None => {
+                    match encode_parquet_bytes(part_rows, &row_metadata_keys, metadata_types, compression)
+                    {
+                        Ok(bytes) => {
+                            if let Err(err) =
+                                storage::put(rt, output_location, &relative_path, bytes)
+                            {
+                                eprintln!("Failed to upload partition {relative_path}: {err}");
+                            }
+                        }
+                        Err(err) => eprintln!("Failed to encode partition {relative_path}: {err}"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Loads digest/bytes pairs from an existing local `dedup_chunks.parquet`
+/// (if any) into `chunk_store` before the scan starts. Without this, a shard
+/// left untouched by `shard_is_unchanged` never re-inserts its chunks into
+/// the in-memory store, yet `write_chunk_store` unconditionally overwrites
+/// `dedup_chunks.parquet` with only what's in the store — silently dropping
+/// every digest the skipped shard still references on disk. Remote outputs
+/// aren't cached here, mirroring the manifest's local-only support.
+fn load_chunk_store(chunk_store: &dedup::ChunkStore, output_location: &Location) -> Result<()> {
+    let Some(output_dir) = output_location.as_local() else {
+        return Ok(());
+    };
+
+    let path = output_dir.join("dedup_chunks.parquet");
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let mut file = StdFile::open(&path)?;
+    let df = ParquetReader::new(&mut file).finish()?;
+    let digest_col = df.column("digest")?.str()?.clone();
+    let bytes_col = df.column("bytes")?.binary()?.clone();
+
+    for idx in 0..df.height() {
+        if let (Some(digest), Some(bytes)) = (digest_col.get(idx), bytes_col.get(idx)) {
+            chunk_store.insert_if_absent(digest, bytes);
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes every unique chunk collected in `chunk_store` to a side Parquet
+/// table (`dedup_chunks.parquet`) keyed by digest, so consumers can
+/// reassemble a file's bytes from its `chunk_digests` column.
+fn write_chunk_store(
+    chunk_store: &dedup::ChunkStore,
+    output_location: &Location,
+    rt: &tokio::runtime::Handle,
+) -> Result<()> {
+    let unique_chunks = chunk_store.unique_chunks();
+    let total_bytes: u64 = unique_chunks.iter().map(|(_, bytes)| bytes.len() as u64).sum();
+    println!(
+        "Deduplicated audio bytes into {} unique chunks ({} bytes)",
+        unique_chunks.len(),
+        total_bytes
+    );
+
+    let digest_data: Vec<String> = unique_chunks.iter().map(|(digest, _)| digest.clone()).collect();
+    let bytes_data: Vec<Vec<u8>> = unique_chunks.into_iter().map(|(_, bytes)| bytes).collect();
+
+    let digest_series = Series::new("digest".into(), digest_data);
+    let bytes_series = Series::new("bytes".into(), bytes_data);
+    let mut df = DataFrame::new(vec![digest_series.into_column(), bytes_series.into_column()])?;
+
+    match output_location.as_local() {
+        Some(output_dir) => {
+            let path = output_dir.join("dedup_chunks.parquet");
+            let mut file = StdFile::create(path)?;
+            ParquetWriter::new(&mut file).finish(&mut df)?;
+        }
+        None => {
+            let mut buffer = Vec::new();
+            ParquetWriter::new(&mut buffer).finish(&mut df)?;
+            storage::put(rt, output_location, "dedup_chunks.parquet", buffer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A file discovered while scanning the input location: either a local path
+/// or an object listed in a remote bucket under the input prefix.
+enum ScanEntry {
+    Local(PathBuf),
+    Remote(storage::ListedObject),
+    Zip {
+        archive_path: PathBuf,
+        entry_name: String,
+    },
+}
+
+/// The normalized path used for metadata matching and the `audio.path`
+/// column, relative to `input_location` when it is local.
+fn local_relative_path_str(file_path: &Path, input_location: &Location) -> String {
+    input_location
+        .as_local()
+        .and_then(|input_dir| file_path.strip_prefix(input_dir).ok())
+        .map(normalized_relative_path)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| {
+            file_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| file_path.to_string_lossy().to_string())
+        })
+}
+
+/// True when every entry in a shard's chunk is a local file whose
+/// manifest-recorded size/mtime still match *and* the manifest was last
+/// saved under the same processing config (`config_digest`), meaning the
+/// shard built from this chunk on a previous run is still fully up to date
+/// and can be left on disk untouched instead of being re-read,
+/// re-transcoded, and rewritten. `status` pairs each entry's relative path
+/// with its current `(size, mtime)` when it's a local file that could be
+/// stat'd, or `None` for remote/ZIP entries and stat failures, which are
+/// never manifest-cached (see `cached_entry` in `main`) and so always force
+/// reprocessing. Requiring the config digest to match keeps a flag change
+/// (e.g. adding `--extract-features` or `--dedup` on a re-run) from being
+/// silently ignored for shards whose member files didn't themselves change.
+fn shard_is_unchanged(
+    manifest: &manifest::Manifest,
+    config_digest: &str,
+    status: &[(String, Option<(u64, u64)>)],
+) -> bool {
+    manifest.config_matches(config_digest)
+        && !status.is_empty()
+        && status.iter().all(|(relative_path_str, local_meta)| {
+            local_meta.is_some_and(|(size, mtime)| {
+                manifest.unchanged(relative_path_str, size, mtime).is_some()
+            })
+        })
+}
+
+/// Hashes the processing-relevant CLI flags — the ones that change what ends
+/// up in a shard's file (dedup, resampling/downmix, target codec, feature and
+/// loudness extraction, embedded-tag reading, partitioning/compression,
+/// duration filtering) — into a digest recorded in the manifest. Scan/filter
+/// flags like `--min-size`, `--exclude` or `--files-per-db` are hashed too:
+/// they change which files end up in a chunk, and `shard_is_unchanged` only
+/// checks that every file now in a chunk is unchanged, not that the chunk
+/// holds the same files as last time. `--min-duration`/`--max-duration` are
+/// applied per-file *after* decoding, inside an already-fixed shard's
+/// processing loop, so a changed duration bound would likewise be silently
+/// ignored by the shard-skip cache if it weren't hashed here.
+fn config_digest(args: &Args) -> String {
+    let descriptor = format!(
+        "dedup={}|dedup_min_size={}|dedup_avg_size={}|dedup_max_size={}|\
+         target_sample_rate={:?}|max_sample_rate={:?}|target_channels={:?}|mono={}|\
+         target_codec={:?}|store_decoded_pcm={}|extract_features={}|features={}|\
+         loudness={}|normalize_lufs={:?}|read_tags={}|format={:?}|\
+         parquet_compression={:?}|partition_by={:?}|max_rows_per_file={:?}|\
+         retain_partition_columns={}|min_duration={:?}|max_duration={:?}|\
+         files_per_db={}|max_depth_size={}|check_mime_type={}|min_size={:?}|\
+         max_size={:?}|exclude={:?}|include={:?}|deref_symlinks={}",
+        args.dedup,
+        args.dedup_min_size,
+        args.dedup_avg_size,
+        args.dedup_max_size,
+        args.target_sample_rate,
+        args.max_sample_rate,
+        args.target_channels,
+        args.mono,
+        args.target_codec,
+        args.store_decoded_pcm,
+        args.extract_features,
+        args.features,
+        args.loudness,
+        args.normalize_lufs,
+        args.read_tags,
+        args.format,
+        args.parquet_compression,
+        args.partition_by,
+        args.max_rows_per_file,
+        args.retain_partition_columns,
+        args.min_duration,
+        args.max_duration,
+        args.files_per_db,
+        args.max_depth_size,
+        args.check_mime_type,
+        args.min_size,
+        args.max_size,
+        args.exclude,
+        args.include,
+        args.deref_symlinks,
+    );
+    blake3::hash(descriptor.as_bytes()).to_hex().to_string()
+}
+
+/// Lists `archive_path`'s entries and pushes the ones passing the size and
+/// glob filters as [`ScanEntry::Zip`] values, so a ZIP archive scans the same
+/// way a folder of loose files does.
+fn expand_zip_entries(
+    archive_path: &Path,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    exclude_patterns: &[glob::Pattern],
+    include_patterns: &[glob::Pattern],
+    files: &mut Vec<ScanEntry>,
+) {
+    let zip_entries = match archive::list_entries(archive_path) {
+        Ok(zip_entries) => zip_entries,
+        Err(err) => {
+            eprintln!("Failed to read zip archive {:?}: {err}", archive_path);
+            return;
+        }
+    };
+
+    for zip_entry in zip_entries {
+        let normalized_entry = normalized_relative_path_str(&zip_entry.name);
+
+        if min_size.is_some_and(|min_size| zip_entry.size < min_size)
+            || max_size.is_some_and(|max_size| zip_entry.size > max_size)
+        {
+            continue;
+        }
+
+        if exclude_patterns
+            .iter()
+            .any(|pattern| pattern.matches(&normalized_entry))
+        {
+            continue;
+        }
+
+        if !include_patterns.is_empty()
+            && !include_patterns
+                .iter()
+                .any(|pattern| pattern.matches(&normalized_entry))
+        {
+            continue;
+        }
+
+        files.push(ScanEntry::Zip {
+            archive_path: archive_path.to_path_buf(),
+            entry_name: zip_entry.name,
+        });
+    }
+}
+
+/// Validates the flag combinations `clap` itself can't express (mutually
+/// exclusive options, range checks that depend on two fields at once).
+/// Extracted out of `main` so each check can be unit tested directly rather
+/// than only through the full CLI.
+fn validate_args(args: &Args) -> Result<()> {
+    if args.dedup && (args.dedup_min_size == 0 || args.dedup_min_size > args.dedup_max_size) {
+        return Err(anyhow::anyhow!(
+            "--dedup-min-size ({}) must be greater than 0 and no larger than \
+             --dedup-max-size ({})",
+            args.dedup_min_size,
+            args.dedup_max_size
+        ));
+    }
+
+    if let Some(channels) = args.target_channels
+        && channels != 1
+    {
+        return Err(anyhow::anyhow!(
+            "--target-channels {channels} is not supported: only `1` (mono) is \
+             implemented today"
+        ));
+    }
+
+    if !args.partition_by.is_empty() && args.format == Format::DuckDB {
+        return Err(anyhow::anyhow!(
+            "--partition-by is only supported with --format parquet; \
+             --format duckdb always writes a single flat file"
+        ));
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
+    if let Some(inspect_path) = &args.inspect {
+        inspect::run(
+            inspect_path,
+            &args.columns,
+            args.head,
+            args.histogram_column.as_deref(),
+            args.verify_audio,
+        )?;
+        return Ok(());
+    }
+
+    validate_args(&args)?;
+
     rayon::ThreadPoolBuilder::new()
         .num_threads(args.num_threads)
         .build_global()?;
 
-    let metadata_store = if let Some(metadata_path) = &args.metadata_file {
+    let mut metadata_store = if let Some(metadata_path) = &args.metadata_file {
         load_metadata_store(metadata_path)?
     } else {
         MetadataStore::new()
     };
+    if args.extract_features {
+        metadata_store.ensure_feature_key();
+    }
+    if args.features {
+        metadata_store.ensure_basic_feature_keys();
+    }
+    if args.loudness {
+        metadata_store.ensure_loudness_key();
+    }
 
-    let metadata_keys = metadata_store.keys.clone();
-    let metadata_types = metadata_store.types.clone();
+    let input = args
+        .input
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("--input is required unless --inspect is set"))?;
+    let output = args
+        .output
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("--output is required unless --inspect is set"))?;
 
-    let metadata_store = Arc::new(metadata_store);
-    let metadata_keys = Arc::new(metadata_keys);
-    let metadata_types = Arc::new(metadata_types);
+    let input_location = Location::parse(input)?;
+    let output_location = Location::parse(output)?;
+    let runtime = Arc::new(storage::build_runtime()?);
 
-    if !args.input.exists() {
-        eprintln!("Input folder does not exist: {:?}", args.input);
-        return Ok(());
-    }
-    if !args.input.is_dir() {
-        eprintln!("Input path is not a directory: {:?}", args.input);
-        return Ok(());
+    if let Some(input_dir) = input_location.as_local() {
+        if !input_dir.exists() {
+            eprintln!("Input folder does not exist: {:?}", input_dir);
+            return Ok(());
+        }
+        if !input_dir.is_dir() && !archive::is_zip_path(input_dir) {
+            eprintln!("Input path is not a directory: {:?}", input_dir);
+            return Ok(());
+        }
     }
 
-    if !args.output.exists() {
-        create_dir_all(&args.output)?;
+    if let Some(output_dir) = output_location.as_local()
+        && !output_dir.exists()
+    {
+        create_dir_all(output_dir)?;
 
-        println!("Created output folder: {:?}", args.output);
+        println!("Created output folder: {:?}", output_dir);
     }
 
+    let manifest_path = output_location
+        .as_local()
+        .map(|output_dir| output_dir.join("manifest.json"));
+    let manifest = Arc::new(std::sync::Mutex::new(
+        manifest_path
+            .as_ref()
+            .filter(|_| !args.force)
+            .map(|path| manifest::Manifest::load(path))
+            .unwrap_or_default(),
+    ));
+    let config_digest = config_digest(&args);
+
     let metadata_relative = args
         .metadata_file
         .as_ref()
-        .and_then(|path| path.strip_prefix(&args.input).ok())
+        .zip(input_location.as_local())
+        .and_then(|(path, input_dir)| path.strip_prefix(input_dir).ok())
         .map(normalized_relative_path);
 
     let metadata_absolute = args
@@ -576,57 +1358,238 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .as_ref()
         .and_then(|path| std::fs::canonicalize(path).ok());
 
-    // Scan the input folder for files
-    let dir = RecursiveDirIterator::with_filter(
-        &args.input,
-        NoSymlink.and(MaxDepth::new(
-            NonZeroUsize::new(args.max_depth_size).unwrap(),
-        )),
-    )?;
+    let exclude_patterns = args
+        .exclude
+        .iter()
+        .map(|pattern| glob::Pattern::new(pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+    let include_patterns = args
+        .include
+        .iter()
+        .map(|pattern| glob::Pattern::new(pattern))
+        .collect::<Result<Vec<_>, _>>()?;
 
     let mut files = Vec::new();
 
-    for entry in dir {
-        if entry.is_dir() {
-            println!("Skipping directory: {:?}", entry);
-            continue;
+    match &input_location {
+        Location::Local(input_dir) if input_dir.is_file() && archive::is_zip_path(input_dir) => {
+            expand_zip_entries(
+                input_dir,
+                args.min_size,
+                args.max_size,
+                &exclude_patterns,
+                &include_patterns,
+                &mut files,
+            );
         }
+        Location::Local(input_dir) => {
+            let mut push_entry = |entry: PathBuf| {
+                if entry.is_dir() {
+                    println!("Skipping directory: {:?}", entry);
+                    return;
+                }
 
-        if let Some(target_relative) = &metadata_relative
-            && let Ok(entry_relative) = entry.strip_prefix(&args.input) {
-                let normalized_entry = normalized_relative_path(entry_relative);
-                if &normalized_entry == target_relative {
-                    println!("Skipping metadata file: {:?}", entry);
-                    continue;
+                if archive::is_zip_path(&entry) {
+                    expand_zip_entries(
+                        &entry,
+                        args.min_size,
+                        args.max_size,
+                        &exclude_patterns,
+                        &include_patterns,
+                        &mut files,
+                    );
+                    return;
+                }
+
+                if let Some(target_relative) = &metadata_relative
+                    && let Ok(entry_relative) = entry.strip_prefix(input_dir)
+                {
+                    let normalized_entry = normalized_relative_path(entry_relative);
+                    if &normalized_entry == target_relative {
+                        println!("Skipping metadata file: {:?}", entry);
+                        return;
+                    }
                 }
-            }
 
-        if let Some(target_abs) = &metadata_absolute
-            && let Ok(entry_abs) = entry.canonicalize()
-                && &entry_abs == target_abs {
+                if let Some(target_abs) = &metadata_absolute
+                    && let Ok(entry_abs) = entry.canonicalize()
+                    && &entry_abs == target_abs
+                {
                     println!("Skipping metadata file: {:?}", entry);
-                    continue;
+                    return;
                 }
 
-        if args.check_mime_type {
-            let mime_type = tree_magic_mini::from_filepath(&entry);
-            if mime_type.is_none() {
-                println!("No mime type found for {:?}", entry);
-                continue;
-            }
+                if args.check_mime_type {
+                    let mime_type = tree_magic_mini::from_filepath(&entry);
+                    if mime_type.is_none() {
+                        println!("No mime type found for {:?}", entry);
+                        return;
+                    }
 
-            let mime_type = mime_type.unwrap();
-            if !AUDIO_MIME_TYPES.contains(&mime_type) {
-                println!("Not an audio file: {:?}: {}", entry, mime_type);
-                continue;
+                    let mime_type = mime_type.unwrap();
+                    if !AUDIO_MIME_TYPES.contains(&mime_type) {
+                        println!("Not an audio file: {:?}: {}", entry, mime_type);
+                        return;
+                    }
+                }
+
+                if let Ok(metadata) = entry.metadata() {
+                    let size = metadata.len();
+                    if args.min_size.is_some_and(|min_size| size < min_size)
+                        || args.max_size.is_some_and(|max_size| size > max_size)
+                    {
+                        println!("Skipping file outside size bounds: {:?}", entry);
+                        return;
+                    }
+                }
+
+                if let Ok(entry_relative) = entry.strip_prefix(input_dir) {
+                    let normalized_entry = normalized_relative_path(entry_relative);
+
+                    if exclude_patterns
+                        .iter()
+                        .any(|pattern| pattern.matches(&normalized_entry))
+                    {
+                        println!("Excluding file: {:?}", entry);
+                        return;
+                    }
+
+                    if !include_patterns.is_empty()
+                        && !include_patterns
+                            .iter()
+                            .any(|pattern| pattern.matches(&normalized_entry))
+                    {
+                        println!("Skipping file not matched by --include: {:?}", entry);
+                        return;
+                    }
+                }
+
+                files.push(ScanEntry::Local(entry));
+            };
+
+            let max_depth = NonZeroUsize::new(args.max_depth_size).unwrap();
+            if args.deref_symlinks {
+                let dir = RecursiveDirIterator::with_filter(input_dir, MaxDepth::new(max_depth))?;
+                for entry in dir {
+                    push_entry(entry);
+                }
+            } else {
+                let dir = RecursiveDirIterator::with_filter(
+                    input_dir,
+                    NoSymlink.and(MaxDepth::new(max_depth)),
+                )?;
+                for entry in dir {
+                    push_entry(entry);
+                }
             }
         }
+        Location::Remote { .. } => {
+            // Mime sniffing for remote objects needs their bytes, so it is
+            // deferred to the per-chunk processing step below. Size and glob
+            // filters don't need the bytes, so they're applied here exactly
+            // like the local/zip branches.
+            for object in storage::list(runtime.handle(), &input_location)? {
+                if args.min_size.is_some_and(|min_size| object.size < min_size)
+                    || args.max_size.is_some_and(|max_size| object.size > max_size)
+                {
+                    println!("Skipping file outside size bounds: {:?}", object.relative_path);
+                    continue;
+                }
+
+                if exclude_patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(&object.relative_path))
+                {
+                    println!("Excluding file: {:?}", object.relative_path);
+                    continue;
+                }
+
+                if !include_patterns.is_empty()
+                    && !include_patterns
+                        .iter()
+                        .any(|pattern| pattern.matches(&object.relative_path))
+                {
+                    println!(
+                        "Skipping file not matched by --include: {:?}",
+                        object.relative_path
+                    );
+                    continue;
+                }
 
-        files.push(entry);
+                files.push(ScanEntry::Remote(object));
+            }
+        }
     }
 
     println!("Found {} files", files.len());
 
+    // When `--read-tags` is set, pre-read each file's embedded tags so their
+    // keys can be folded into the metadata schema before any shard is
+    // written, mirroring how an explicit `--metadata-file` seeds the schema.
+    let mut tag_cache: HashMap<String, HashMap<String, Value>> = HashMap::new();
+    if args.read_tags {
+        for entry in &files {
+            let (relative_path, buffer) = match entry {
+                ScanEntry::Local(file_path) => {
+                    let buffer = match std::fs::read(file_path) {
+                        Ok(buffer) => buffer,
+                        Err(err) => {
+                            eprintln!("Failed to read {:?} for tag extraction: {err}", file_path);
+                            continue;
+                        }
+                    };
+                    (local_relative_path_str(file_path, &input_location), buffer)
+                }
+                ScanEntry::Remote(object) => {
+                    let buffer = match storage::get(runtime.handle(), &input_location, object) {
+                        Ok(buffer) => buffer,
+                        Err(err) => {
+                            eprintln!(
+                                "Failed to fetch {:?} for tag extraction: {err}",
+                                object.relative_path
+                            );
+                            continue;
+                        }
+                    };
+                    (object.relative_path.clone(), buffer)
+                }
+                ScanEntry::Zip {
+                    archive_path,
+                    entry_name,
+                } => {
+                    let buffer = match archive::read_entry(archive_path, entry_name) {
+                        Ok(buffer) => buffer,
+                        Err(err) => {
+                            eprintln!(
+                                "Failed to read {entry_name:?} from {archive_path:?} for tag extraction: {err}"
+                            );
+                            continue;
+                        }
+                    };
+                    (normalized_relative_path_str(entry_name), buffer)
+                }
+            };
+
+            let file_tags = tags::read_embedded_tags(&buffer);
+            if !file_tags.is_empty() {
+                metadata_store.update_types_from_record(&file_tags);
+                tag_cache.insert(relative_path, file_tags);
+            }
+        }
+    }
+
+    let metadata_keys = metadata_store.keys.clone();
+    let metadata_types = metadata_store.types.clone();
+
+    let metadata_store = Arc::new(metadata_store);
+    let metadata_keys = Arc::new(metadata_keys);
+    let metadata_types = Arc::new(metadata_types);
+    let tag_cache = Arc::new(tag_cache);
+    let chunk_store = Arc::new(dedup::ChunkStore::new());
+    if args.dedup && !args.force {
+        load_chunk_store(&chunk_store, &output_location)?;
+    }
+
     // Chunk the files into groups of `args.files_per_db`
     files
         .chunks(args.files_per_db)
@@ -637,86 +1600,381 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Format::DuckDB => "duckdb",
                 Format::Parquet => "parquet",
             };
-            let path = args.output.join(format!("{}.{}", idx, ext));
+            let shard_name = format!("{}.{}", idx, ext);
+            let local_shard_path = output_location
+                .as_local()
+                .map(|output_dir| output_dir.join(&shard_name));
+
+            if !args.force
+                && local_shard_path.as_ref().is_some_and(|path| path.exists())
+            {
+                let status: Vec<(String, Option<(u64, u64)>)> = chunk
+                    .iter()
+                    .map(|scan_entry| match scan_entry {
+                        ScanEntry::Local(file_path) => {
+                            let relative_path_str =
+                                local_relative_path_str(file_path, &input_location);
+                            let local_meta = std::fs::metadata(file_path)
+                                .ok()
+                                .map(|meta| (meta.len(), manifest::truncated_mtime(&meta)));
+                            (relative_path_str, local_meta)
+                        }
+                        ScanEntry::Remote(object) => (object.relative_path.clone(), None),
+                        ScanEntry::Zip { entry_name, .. } => {
+                            (normalized_relative_path_str(entry_name), None)
+                        }
+                    })
+                    .collect();
+
+                if shard_is_unchanged(&manifest.lock().unwrap(), &config_digest, &status) {
+                    println!(
+                        "Shard {} unchanged since last run, skipping: {:?}",
+                        idx,
+                        local_shard_path.as_ref().unwrap()
+                    );
+                    return;
+                }
+            }
 
             println!(
                 "Creating database {} and adding {} files to it",
-                path.display(),
+                local_shard_path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| shard_name.clone()),
                 args.files_per_db
             );
 
-            if path.exists() {
+            if let Some(path) = &local_shard_path
+                && path.exists()
+            {
                 println!("Removing existing file: {:?}", path);
-                std::fs::remove_file(&path).unwrap();
+                std::fs::remove_file(path).unwrap();
             }
 
             let mut files = Vec::new();
-            for file_path in chunk {
-                let mut file = std::fs::File::open(file_path.clone()).unwrap();
-                let mut buffer = Vec::new();
-                file.read_to_end(&mut buffer).unwrap();
-
-                let relative_path_str = {
-                    let normalized_relative = file_path
-                        .strip_prefix(&args.input)
-                        .map(normalized_relative_path)
-                        .unwrap_or_else(|_| normalized_relative_path(file_path));
-
-                    if normalized_relative.is_empty() {
-                        file_path
-                            .file_name()
-                            .and_then(|name| name.to_str())
-                            .map(|s| s.to_string())
-                            .unwrap_or_else(|| file_path.to_string_lossy().to_string())
-                    } else {
-                        normalized_relative
+            let mut shard_total_bytes: u64 = 0;
+            let mut shard_new_bytes: u64 = 0;
+            for scan_entry in chunk {
+                let (relative_path_str, buffer, local_meta) = match scan_entry {
+                    ScanEntry::Local(file_path) => {
+                        let mut file = std::fs::File::open(file_path.clone()).unwrap();
+                        let mut buffer = Vec::new();
+                        file.read_to_end(&mut buffer).unwrap();
+
+                        let relative_path_str = local_relative_path_str(file_path, &input_location);
+                        let local_meta = std::fs::metadata(file_path)
+                            .ok()
+                            .map(|meta| (meta.len(), manifest::truncated_mtime(&meta)));
+
+                        (relative_path_str, buffer, local_meta)
                     }
-                };
+                    ScanEntry::Remote(object) => {
+                        let buffer = match storage::get(runtime.handle(), &input_location, object)
+                        {
+                            Ok(buffer) => buffer,
+                            Err(err) => {
+                                eprintln!(
+                                    "Failed to fetch {:?} from object store: {err}",
+                                    object.relative_path
+                                );
+                                continue;
+                            }
+                        };
+
+                        if args.check_mime_type {
+                            let mime_type = tree_magic_mini::from_u8(&buffer);
+                            if !AUDIO_MIME_TYPES.contains(&mime_type) {
+                                println!(
+                                    "Not an audio file: {:?}: {}",
+                                    object.relative_path, mime_type
+                                );
+                                continue;
+                            }
+                        }
 
-                let (duration, sr) = match WavReader::new(&buffer[..]) {
-                    Ok(reader) => {
-                        let spec = reader.spec();
-                        (
-                            reader.duration() as f64 / spec.sample_rate as f64,
-                            spec.sample_rate as i32,
-                        )
+                        (object.relative_path.clone(), buffer, None)
+                    }
+                    ScanEntry::Zip {
+                        archive_path,
+                        entry_name,
+                    } => {
+                        let buffer = match archive::read_entry(archive_path, entry_name) {
+                            Ok(buffer) => buffer,
+                            Err(err) => {
+                                eprintln!(
+                                    "Failed to read {entry_name:?} from {archive_path:?}: {err}"
+                                );
+                                continue;
+                            }
+                        };
+
+                        if args.check_mime_type {
+                            let mime_type = tree_magic_mini::from_u8(&buffer);
+                            if !AUDIO_MIME_TYPES.contains(&mime_type) {
+                                println!("Not an audio file: {entry_name:?}: {mime_type}");
+                                continue;
+                            }
+                        }
+
+                        (normalized_relative_path_str(entry_name), buffer, None)
                     }
-                    Err(_) => (0.0, 0),
                 };
 
-                let file_name = match file_path.file_name().and_then(|s| s.to_str()) {
-                    Some(name) => name.to_string(),
-                    None => {
-                        eprintln!(
-                            "Could not get file name as a string for {:?}, skipping.",
-                            file_path
-                        );
-                        continue;
+                let cached_entry = local_meta.and_then(|(size, mtime)| {
+                    manifest
+                        .lock()
+                        .unwrap()
+                        .unchanged(&relative_path_str, size, mtime)
+                        .cloned()
+                });
+
+                let (duration, sr, digest) = if let Some(entry) = cached_entry {
+                    (entry.duration, entry.sampling_rate, entry.digest)
+                } else {
+                    let (duration, sr) = decode::duration_and_sample_rate(&buffer);
+                    (duration, sr, blake3::hash(&buffer).to_hex().to_string())
+                };
+
+                if let Some((size, mtime)) = local_meta {
+                    manifest.lock().unwrap().record(
+                        relative_path_str.clone(),
+                        manifest::ManifestEntry {
+                            size,
+                            mtime,
+                            duration,
+                            sampling_rate: sr,
+                            digest,
+                        },
+                    );
+                }
+
+                if args.min_duration.is_some_and(|min_duration| duration < min_duration)
+                    || args.max_duration.is_some_and(|max_duration| duration > max_duration)
+                {
+                    println!(
+                        "Skipping file outside duration bounds: {}",
+                        relative_path_str
+                    );
+                    continue;
+                }
+
+                // `--max-sample-rate` only caps clips above it; `--target-sample-rate`
+                // always resamples. When both are set, the lower of the two wins.
+                let effective_target_rate = match (args.target_sample_rate, args.max_sample_rate) {
+                    (Some(target), Some(max_rate)) => Some(target.min(max_rate)),
+                    (Some(target), None) => Some(target),
+                    (None, Some(max_rate)) if sr > max_rate as i32 => Some(max_rate),
+                    (None, Some(_)) | (None, None) => None,
+                };
+
+                // `--mono` is shorthand for `--target-channels 1`; an explicit
+                // `--target-channels` wins if both are somehow set.
+                let effective_target_channels = args.target_channels.or(if args.mono {
+                    Some(1)
+                } else {
+                    None
+                });
+
+                // `--store-decoded-pcm` re-encodes every clip to canonical PCM
+                // WAV bytes instead of embedding the original (possibly
+                // compressed) file bytes, unless a more specific target codec
+                // was already requested.
+                let effective_target_codec = if args.store_decoded_pcm
+                    && args.target_codec == TargetCodec::PassThrough
+                {
+                    TargetCodec::Wav
+                } else {
+                    args.target_codec
+                };
+
+                let needs_transcode = effective_target_rate.is_some()
+                    || effective_target_channels.is_some()
+                    || effective_target_codec != TargetCodec::PassThrough;
+
+                let (mut buffer, sr, duration) = if needs_transcode {
+                    match transcode::transcode(
+                        &buffer,
+                        sr,
+                        effective_target_rate,
+                        effective_target_channels,
+                        effective_target_codec,
+                    ) {
+                        Ok((transcoded_bytes, new_sr)) => {
+                            let new_duration = decode::duration_and_sample_rate(&transcoded_bytes).0;
+                            (transcoded_bytes, new_sr, new_duration)
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to transcode {relative_path_str}: {err}");
+                            (buffer, sr, duration)
+                        }
                     }
+                } else {
+                    (buffer, sr, duration)
                 };
 
-                let metadata = metadata_store.metadata_for_file(&relative_path_str, &file_name);
+                let file_name = relative_path_str
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(&relative_path_str)
+                    .to_string();
 
-                let file = File {
-                    duration,
-                    audio: Audio {
+                let mut metadata = metadata_store.metadata_for_file(&relative_path_str, &file_name);
+                if let Some(file_tags) = tag_cache.get(&relative_path_str) {
+                    apply_tag_fallback(&mut metadata, file_tags);
+                }
+
+                if args.extract_features {
+                    match decode::decode_pcm(&buffer) {
+                        Ok((samples, pcm_rate, channels)) => {
+                            let feature_vector = features::extract(&samples, channels, pcm_rate);
+                            metadata.insert(
+                                "audio_features".to_string(),
+                                Value::Array(
+                                    feature_vector
+                                        .into_iter()
+                                        .map(|value| serde_json::json!(value))
+                                        .collect(),
+                                ),
+                            );
+                        }
+                        Err(err) => {
+                            eprintln!(
+                                "Failed to extract audio features for {relative_path_str}: {err}"
+                            );
+                        }
+                    }
+                }
+
+                if args.features {
+                    match decode::decode_pcm(&buffer) {
+                        Ok((samples, pcm_rate, channels)) => {
+                            let (rms, zero_crossing_rate, spectral_centroid) =
+                                features::extract_basic(&samples, channels, pcm_rate);
+                            metadata.insert("rms".to_string(), serde_json::json!(rms));
+                            metadata.insert(
+                                "zero_crossing_rate".to_string(),
+                                serde_json::json!(zero_crossing_rate),
+                            );
+                            metadata.insert(
+                                "spectral_centroid".to_string(),
+                                serde_json::json!(spectral_centroid),
+                            );
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to compute audio features for {relative_path_str}: {err}");
+                        }
+                    }
+                }
+
+                if args.loudness || args.normalize_lufs.is_some() {
+                    match decode::decode_pcm(&buffer) {
+                        Ok((mut samples, pcm_rate, channels)) => {
+                            let measured =
+                                loudness::measure_integrated_loudness(&samples, channels, pcm_rate);
+                            if args.loudness {
+                                metadata.insert("lufs".to_string(), serde_json::json!(measured));
+                            }
+
+                            if let Some(target_lufs) = args.normalize_lufs
+                                && measured.is_finite()
+                            {
+                                let gain = loudness::normalization_gain(measured, target_lufs);
+                                loudness::apply_gain(&mut samples, gain);
+                                // Gain-adjusted samples must be re-encoded; with no
+                                // explicit `--target-codec`, `effective_target_codec`
+                                // is `PassThrough`, which `encode_pcm` maps to WAV (see
+                                // `normalize_lufs`'s doc comment).
+                                match transcode::encode_pcm(
+                                    &samples,
+                                    channels,
+                                    pcm_rate,
+                                    effective_target_codec,
+                                ) {
+                                    Ok(normalized_bytes) => buffer = normalized_bytes,
+                                    Err(err) => eprintln!(
+                                        "Failed to re-encode {relative_path_str} after loudness normalization: {err}"
+                                    ),
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            eprintln!("Failed to measure loudness for {relative_path_str}: {err}");
+                        }
+                    }
+                }
+
+                let audio = if args.dedup {
+                    let chunks = dedup::chunk_bytes(
+                        &buffer,
+                        args.dedup_min_size,
+                        args.dedup_max_size,
+                        args.dedup_avg_size,
+                    );
+
+                    let mut offset = 0usize;
+                    let mut digests = Vec::with_capacity(chunks.len());
+                    for chunk in &chunks {
+                        let slice = &buffer[offset..offset + chunk.len];
+                        shard_total_bytes += chunk.len as u64;
+                        if chunk_store.insert_if_absent(&chunk.digest, slice) {
+                            shard_new_bytes += chunk.len as u64;
+                        }
+                        digests.push(chunk.digest.clone());
+                        offset += chunk.len;
+                    }
+
+                    Audio {
+                        path: relative_path_str,
+                        sampling_rate: sr,
+                        chunk_total_len: Some(buffer.len() as u64),
+                        bytes: Vec::new(),
+                        chunk_digests: Some(digests),
+                    }
+                } else {
+                    Audio {
                         path: relative_path_str,
                         sampling_rate: sr,
                         bytes: buffer,
-                    },
+                        chunk_digests: None,
+                        chunk_total_len: None,
+                    }
+                };
+
+                let file = File {
+                    duration,
+                    audio,
                     metadata,
                 };
 
                 files.push(file);
             }
 
+            if args.dedup && shard_total_bytes > 0 {
+                let dedup_ratio = shard_new_bytes as f64 / shard_total_bytes as f64;
+                println!(
+                    "Shard {}: deduplicated {:.1}% of chunk bytes ({} unique / {} total)",
+                    idx,
+                    (1.0 - dedup_ratio) * 100.0,
+                    shard_new_bytes,
+                    shard_total_bytes
+                );
+            }
+
             if args.format == Format::DuckDB {
-                let conn = Connection::open(&path).unwrap();
-                let create_sql =
-                    build_create_table_sql(metadata_keys.as_ref(), metadata_types.as_ref());
+                let db_path = local_shard_path.clone().unwrap_or_else(|| {
+                    std::env::temp_dir().join(format!("audios-to-dataset-{idx}.duckdb"))
+                });
+
+                let conn = Connection::open(&db_path).unwrap();
+                let create_sql = build_create_table_sql(
+                    metadata_keys.as_ref(),
+                    metadata_types.as_ref(),
+                    args.dedup,
+                );
                 conn.execute_batch(&create_sql).unwrap();
 
-                let insert_sql = build_insert_sql(metadata_keys.as_ref());
+                let insert_sql = build_insert_sql(metadata_keys.as_ref(), args.dedup);
                 let mut insert_stmt = conn.prepare(&insert_sql).unwrap();
 
                 conn.execute_batch("BEGIN TRANSACTION").unwrap();
@@ -728,6 +1986,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     params.push(DuckValue::from(file.audio.sampling_rate));
                     params.push(DuckValue::from(file.audio.bytes.clone()));
 
+                    if args.dedup {
+                        let digests_json = file
+                            .audio
+                            .chunk_digests
+                            .as_ref()
+                            .map(|digests| serde_json::to_string(digests).unwrap_or_default());
+                        params.push(DuckValue::from(digests_json));
+                        params.push(DuckValue::from(
+                            file.audio.chunk_total_len.map(|len| len as i64),
+                        ));
+                    }
+
                     for key in metadata_keys.iter() {
                         let column_type = metadata_types
                             .get(key)
@@ -748,6 +2018,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     _ => v.to_string(),
                                 })));
                             }
+                            MetadataType::FloatArray => {
+                                let list_value = value
+                                    .and_then(|v| v.as_array())
+                                    .map(|items| {
+                                        DuckValue::List(
+                                            items
+                                                .iter()
+                                                .filter_map(|item| item.as_f64())
+                                                .map(DuckValue::Double)
+                                                .collect(),
+                                        )
+                                    })
+                                    .unwrap_or(DuckValue::Null);
+                                params.push(list_value);
+                            }
                         }
                     }
 
@@ -758,17 +2043,74 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 if let Err(e) = conn.close() {
                     eprintln!("Failed to close connection: {:?}", e);
                 }
+
+                if local_shard_path.is_none() {
+                    let bytes = std::fs::read(&db_path).unwrap();
+                    if let Err(err) =
+                        storage::put(runtime.handle(), &output_location, &shard_name, bytes)
+                    {
+                        eprintln!("Failed to upload {shard_name} to object store: {err}");
+                    }
+                    let _ = std::fs::remove_file(&db_path);
+                }
             } else if args.format == Format::Parquet {
-                let _ = write_files_to_parquet(
-                    path.clone(),
-                    &files,
-                    metadata_keys.as_ref(),
-                    metadata_types.as_ref(),
-                    args.parquet_compression,
-                );
+                if args.partition_by.is_empty() {
+                    match &local_shard_path {
+                        Some(path) => {
+                            let _ = write_files_to_parquet(
+                                path.clone(),
+                                &files,
+                                metadata_keys.as_ref(),
+                                metadata_types.as_ref(),
+                                args.parquet_compression,
+                            );
+                        }
+                        None => match encode_parquet_bytes(
+                            &files,
+                            metadata_keys.as_ref(),
+                            metadata_types.as_ref(),
+                            args.parquet_compression,
+                        ) {
+                            Ok(bytes) => {
+                                if let Err(err) = storage::put(
+                                    runtime.handle(),
+                                    &output_location,
+                                    &shard_name,
+                                    bytes,
+                                ) {
+                                    eprintln!("Failed to upload {shard_name} to object store: {err}");
+                                }
+                            }
+                            Err(err) => eprintln!("Failed to encode {shard_name}: {err}"),
+                        },
+                    }
+                } else {
+                    write_partitioned_parquet(
+                        &output_location,
+                        idx,
+                        &files,
+                        &args.partition_by,
+                        args.max_rows_per_file,
+                        args.retain_partition_columns,
+                        metadata_keys.as_ref(),
+                        metadata_types.as_ref(),
+                        args.parquet_compression,
+                        runtime.handle(),
+                    );
+                }
             }
         });
 
+    if args.dedup {
+        write_chunk_store(&chunk_store, &output_location, runtime.handle())?;
+    }
+
+    if let Some(path) = &manifest_path {
+        let mut manifest = manifest.lock().unwrap();
+        manifest.set_config_digest(config_digest.clone());
+        manifest.save(path)?;
+    }
+
     Ok(())
 }
 
@@ -827,6 +2169,8 @@ mod tests {
                 path: "clip.wav".to_string(),
                 sampling_rate: 16_000,
                 bytes: bytes.clone(),
+                chunk_digests: None,
+                chunk_total_len: None,
             },
             metadata,
         }];
@@ -878,6 +2222,343 @@ mod tests {
         Ok(())
     }
 
+    fn sine_tone_for_tests(frequency: f64, sample_rate: u32, seconds: f64) -> Vec<f32> {
+        let frame_count = (sample_rate as f64 * seconds) as usize;
+        (0..frame_count)
+            .map(|i| (2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64).sin() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn extract_computes_sane_rms_and_centroid_for_a_tone() {
+        let sample_rate = 16_000;
+        let frequency = 440.0;
+        let samples = sine_tone_for_tests(frequency, sample_rate, 1.0);
+
+        let feature_vector = features::extract(&samples, 1, sample_rate);
+        assert_eq!(feature_vector.len(), features::FEATURE_DIMENSION);
+
+        let rms = feature_vector[1];
+        let zero_crossing_rate = feature_vector[2];
+        let centroid = feature_vector[3];
+
+        // A full-scale sine has RMS of 1/sqrt(2); allow generous tolerance
+        // for windowing/framing effects.
+        assert!((0.5..0.9).contains(&rms), "unexpected rms: {rms}");
+        assert!((0.0..1.0).contains(&zero_crossing_rate));
+        assert!((frequency - 100.0..frequency + 100.0).contains(&centroid), "unexpected centroid: {centroid}");
+    }
+
+    #[test]
+    fn write_files_to_parquet_persists_audio_features() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let output_path = temp_dir.path().join("sample.parquet");
+
+        let feature_vector: Vec<f64> = (0..features::FEATURE_DIMENSION as i64).map(|v| v as f64).collect();
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "audio_features".to_string(),
+            Value::Array(
+                feature_vector
+                    .iter()
+                    .map(|value| serde_json::json!(value))
+                    .collect(),
+            ),
+        );
+
+        let mut metadata_types = HashMap::new();
+        metadata_types.insert("audio_features".to_string(), MetadataType::FloatArray);
+        let metadata_keys = BTreeSet::from(["audio_features".to_string()]);
+
+        let files = vec![File {
+            duration: 1.25,
+            audio: Audio {
+                path: "clip.wav".to_string(),
+                sampling_rate: 16_000,
+                bytes: vec![0_u8, 1, 2, 3],
+                chunk_digests: None,
+                chunk_total_len: None,
+            },
+            metadata,
+        }];
+
+        write_files_to_parquet(
+            &output_path,
+            &files,
+            &metadata_keys,
+            &metadata_types,
+            ParquetCompressionChoice::Snappy,
+        )?;
+
+        let mut file = StdFile::open(&output_path)?;
+        let df = ParquetReader::new(&mut file).finish()?;
+
+        let column = df.column("audio_features")?.list()?;
+        let row = column.get_as_series(0).expect("feature row to exist");
+        assert_eq!(row.len(), features::FEATURE_DIMENSION);
+
+        let stored: Vec<f64> = row.f64()?.into_no_null_iter().collect();
+        assert_eq!(stored, feature_vector);
+
+        Ok(())
+    }
+
+    #[test]
+    fn extract_basic_computes_sane_values_for_a_tone() {
+        let sample_rate = 16_000;
+        let frequency = 440.0;
+        let samples = sine_tone_for_tests(frequency, sample_rate, 1.0);
+
+        let (rms, zero_crossing_rate, centroid) = features::extract_basic(&samples, 1, sample_rate);
+
+        assert!((0.5..0.9).contains(&rms), "unexpected rms: {rms}");
+        assert!((0.0..1.0).contains(&zero_crossing_rate));
+        assert!((frequency - 100.0..frequency + 100.0).contains(&centroid), "unexpected centroid: {centroid}");
+    }
+
+    #[test]
+    fn write_files_to_parquet_persists_basic_feature_columns() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let output_path = temp_dir.path().join("sample.parquet");
+
+        let mut metadata = HashMap::new();
+        metadata.insert("rms".to_string(), serde_json::json!(0.2));
+        metadata.insert("zero_crossing_rate".to_string(), serde_json::json!(0.1));
+        metadata.insert("spectral_centroid".to_string(), serde_json::json!(1500.0));
+
+        let mut metadata_types = HashMap::new();
+        for key in ["rms", "zero_crossing_rate", "spectral_centroid"] {
+            metadata_types.insert(key.to_string(), MetadataType::Float64);
+        }
+        let metadata_keys = BTreeSet::from([
+            "rms".to_string(),
+            "zero_crossing_rate".to_string(),
+            "spectral_centroid".to_string(),
+        ]);
+
+        let files = vec![File {
+            duration: 1.0,
+            audio: Audio {
+                path: "clip.wav".to_string(),
+                sampling_rate: 16_000,
+                bytes: vec![0_u8, 1, 2, 3],
+                chunk_digests: None,
+                chunk_total_len: None,
+            },
+            metadata,
+        }];
+
+        write_files_to_parquet(
+            &output_path,
+            &files,
+            &metadata_keys,
+            &metadata_types,
+            ParquetCompressionChoice::Snappy,
+        )?;
+
+        let mut file = StdFile::open(&output_path)?;
+        let df = ParquetReader::new(&mut file).finish()?;
+
+        assert_eq!(df.column("rms")?.f64()?.get(0), Some(0.2));
+        assert_eq!(df.column("zero_crossing_rate")?.f64()?.get(0), Some(0.1));
+        assert_eq!(df.column("spectral_centroid")?.f64()?.get(0), Some(1500.0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_partitioned_parquet_splits_by_metadata_value() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let output_location = Location::Local(temp_dir.path().to_path_buf());
+        let runtime = storage::build_runtime()?;
+
+        let mut metadata_types = HashMap::new();
+        metadata_types.insert("speaker".to_string(), MetadataType::String);
+        let metadata_keys = BTreeSet::from(["speaker".to_string()]);
+
+        let make_file = |path: &str, speaker: &str| {
+            let mut metadata = HashMap::new();
+            metadata.insert("speaker".to_string(), Value::String(speaker.to_string()));
+            File {
+                duration: 1.0,
+                audio: Audio {
+                    path: path.to_string(),
+                    sampling_rate: 16_000,
+                    bytes: vec![0_u8, 1, 2, 3],
+                    chunk_digests: None,
+                    chunk_total_len: None,
+                },
+                metadata,
+            }
+        };
+
+        let files = vec![
+            make_file("a.wav", "alice"),
+            make_file("b.wav", "alice"),
+            make_file("c.wav", "bob"),
+        ];
+
+        write_partitioned_parquet(
+            &output_location,
+            0,
+            &files,
+            &["speaker".to_string()],
+            None,
+            false,
+            &metadata_keys,
+            &metadata_types,
+            ParquetCompressionChoice::Snappy,
+            runtime.handle(),
+        );
+
+        let alice_path = temp_dir
+            .path()
+            .join("speaker=alice")
+            .join("part-0-0.parquet");
+        let bob_path = temp_dir.path().join("speaker=bob").join("part-0-0.parquet");
+        assert!(alice_path.exists());
+        assert!(bob_path.exists());
+
+        let mut alice_file = StdFile::open(&alice_path)?;
+        let alice_df = ParquetReader::new(&mut alice_file).finish()?;
+        assert_eq!(alice_df.height(), 2);
+        assert!(alice_df.column("speaker").is_err());
+
+        let mut bob_file = StdFile::open(&bob_path)?;
+        let bob_df = ParquetReader::new(&mut bob_file).finish()?;
+        assert_eq!(bob_df.height(), 1);
+
+        Ok(())
+    }
+
+    /// An `Args` with every field at its `clap` default, so a test only has
+    /// to override the one or two fields it cares about.
+    fn test_args() -> Args {
+        Args {
+            input: None,
+            format: Format::Parquet,
+            files_per_db: 500,
+            max_depth_size: 50,
+            check_mime_type: false,
+            num_threads: 5,
+            output: None,
+            parquet_compression: ParquetCompressionChoice::Snappy,
+            metadata_file: None,
+            read_tags: false,
+            dedup: false,
+            dedup_min_size: 4096,
+            dedup_avg_size: 16384,
+            dedup_max_size: 65536,
+            force: false,
+            min_size: None,
+            max_size: None,
+            min_duration: None,
+            max_duration: None,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            deref_symlinks: false,
+            target_sample_rate: None,
+            max_sample_rate: None,
+            target_channels: None,
+            mono: false,
+            target_codec: TargetCodec::PassThrough,
+            store_decoded_pcm: false,
+            extract_features: false,
+            features: false,
+            loudness: false,
+            normalize_lufs: None,
+            partition_by: Vec::new(),
+            max_rows_per_file: None,
+            retain_partition_columns: false,
+            inspect: None,
+            columns: Vec::new(),
+            head: None,
+            histogram_column: None,
+            verify_audio: false,
+        }
+    }
+
+    #[test]
+    fn validate_args_accepts_defaults() {
+        assert!(validate_args(&test_args()).is_ok());
+    }
+
+    #[test]
+    fn validate_args_rejects_dedup_min_size_above_max_size() {
+        let args = Args {
+            dedup: true,
+            dedup_min_size: 100,
+            dedup_max_size: 50,
+            ..test_args()
+        };
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn validate_args_rejects_zero_dedup_min_size() {
+        let args = Args {
+            dedup: true,
+            dedup_min_size: 0,
+            ..test_args()
+        };
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn validate_args_rejects_non_mono_target_channels() {
+        let args = Args {
+            target_channels: Some(2),
+            ..test_args()
+        };
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn validate_args_accepts_mono_target_channels() {
+        let args = Args {
+            target_channels: Some(1),
+            ..test_args()
+        };
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn validate_args_rejects_partition_by_with_duckdb_format() {
+        let args = Args {
+            format: Format::DuckDB,
+            partition_by: vec!["speaker".to_string()],
+            ..test_args()
+        };
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn validate_args_accepts_partition_by_with_parquet_format() {
+        let args = Args {
+            format: Format::Parquet,
+            partition_by: vec!["speaker".to_string()],
+            ..test_args()
+        };
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn sanitize_partition_value_strips_slashes_and_traversal_components() {
+        assert_eq!(sanitize_partition_value("a/b"), "a_b");
+        assert_eq!(sanitize_partition_value(r"a\b"), "a_b");
+        assert_eq!(sanitize_partition_value(".."), "_unknown");
+        assert_eq!(sanitize_partition_value("."), "_unknown");
+        assert_eq!(sanitize_partition_value(""), "_unknown");
+
+        // "../../etc" can no longer escape the partition directory once its
+        // slashes are stripped: it becomes a single opaque path segment, not
+        // a multi-level `..` traversal.
+        let sanitized = sanitize_partition_value("../../etc");
+        assert!(!sanitized.contains('/') && !sanitized.contains('\\'));
+        assert_ne!(sanitized, ".");
+        assert_ne!(sanitized, "..");
+    }
+
     #[test]
     fn load_jsonl_metadata_uses_relative_path_matching() -> anyhow::Result<()> {
         let temp_dir = tempdir()?;
@@ -897,4 +2578,400 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn load_csv_metadata_infers_numeric_column_as_typed_parquet_column() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let metadata_path = temp_dir.path().join("metadata.csv");
+        std::fs::write(
+            &metadata_path,
+            "relative_path,score\nclip.wav,0.75\nother.wav,0.25\n",
+        )?;
+
+        let store = load_metadata_store(&metadata_path)?;
+        let metadata = store.metadata_for_file("clip.wav", "clip.wav");
+
+        assert_eq!(
+            metadata.get("score").and_then(|v| v.as_f64()),
+            Some(0.75)
+        );
+        assert_eq!(store.types.get("score"), Some(&MetadataType::Float64));
+
+        let files = vec![File {
+            duration: 1.0,
+            audio: Audio {
+                path: "clip.wav".to_string(),
+                sampling_rate: 16_000,
+                bytes: vec![0, 1, 2],
+                chunk_digests: None,
+                chunk_total_len: None,
+            },
+            metadata,
+        }];
+        let (df, _, _) = build_output_dataframe(&files, &store.keys, &store.types, ParquetCompressionChoice::Snappy)?;
+        assert_eq!(df.column("score")?.f64()?.get(0), Some(0.75));
+
+        Ok(())
+    }
+
+    fn build_companded_wav(format_tag: u16, data: &[u8], sample_rate: u32, channels: u16) -> Vec<u8> {
+        let mut fmt_chunk = Vec::new();
+        fmt_chunk.extend_from_slice(&format_tag.to_le_bytes());
+        fmt_chunk.extend_from_slice(&channels.to_le_bytes());
+        fmt_chunk.extend_from_slice(&sample_rate.to_le_bytes());
+        let byte_rate = sample_rate * channels as u32;
+        fmt_chunk.extend_from_slice(&byte_rate.to_le_bytes());
+        fmt_chunk.extend_from_slice(&(channels).to_le_bytes()); // block_align (1 byte/sample * channels)
+        fmt_chunk.extend_from_slice(&8u16.to_le_bytes()); // bits_per_sample
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // riff size, unused by our parser
+        bytes.extend_from_slice(b"WAVE");
+
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&(fmt_chunk.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&fmt_chunk);
+
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+
+        bytes
+    }
+
+    #[test]
+    fn decode_pcm_expands_mulaw_wav_to_linear_samples() {
+        let bytes = build_companded_wav(7, &[0xFF, 0x7F], 8_000, 1);
+
+        let (duration, sample_rate) = decode::duration_and_sample_rate(&bytes);
+        assert_eq!(sample_rate, 8_000);
+        assert!((duration - 2.0 / 8_000.0).abs() < f64::EPSILON);
+
+        let (samples, sr, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(sr, 8_000);
+        assert_eq!(channels, 1);
+        assert_eq!(samples.len(), 2);
+        assert!(samples[0].abs() < 0.01);
+    }
+
+    #[test]
+    fn apply_tag_fallback_does_not_override_explicit_metadata() {
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "artist".to_string(),
+            Value::String("from metadata file".to_string()),
+        );
+
+        let mut tags = HashMap::new();
+        tags.insert(
+            "artist".to_string(),
+            Value::String("from embedded tags".to_string()),
+        );
+        tags.insert(
+            "album".to_string(),
+            Value::String("from embedded tags".to_string()),
+        );
+
+        apply_tag_fallback(&mut metadata, &tags);
+
+        assert_eq!(
+            metadata.get("artist").and_then(|v| v.as_str()),
+            Some("from metadata file")
+        );
+        assert_eq!(
+            metadata.get("album").and_then(|v| v.as_str()),
+            Some("from embedded tags")
+        );
+    }
+
+    #[test]
+    fn decode_pcm_expands_alaw_wav_to_linear_samples() {
+        let bytes = build_companded_wav(6, &[0xD5, 0x55], 8_000, 1);
+
+        let (samples, sr, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(sr, 8_000);
+        assert_eq!(channels, 1);
+        assert_eq!(samples.len(), 2);
+        assert!(samples[0].abs() < 0.01);
+    }
+
+    /// Wraps `format_tag` in a `WAVEFORMATEXTENSIBLE` `fmt ` chunk (the
+    /// sub-format GUID's first two bytes carry the real format tag, the
+    /// remaining 14 bytes are the fixed PCM GUID suffix), mirroring what
+    /// writers like Audacity/FFmpeg emit for >16-bit or multichannel WAVs.
+    fn build_extensible_wav(format_tag: u16, data: &[u8], sample_rate: u32, channels: u16) -> Vec<u8> {
+        let mut fmt_chunk = Vec::new();
+        fmt_chunk.extend_from_slice(&0xFFFEu16.to_le_bytes()); // WAVE_FORMAT_EXTENSIBLE
+        fmt_chunk.extend_from_slice(&channels.to_le_bytes());
+        fmt_chunk.extend_from_slice(&sample_rate.to_le_bytes());
+        let byte_rate = sample_rate * channels as u32;
+        fmt_chunk.extend_from_slice(&byte_rate.to_le_bytes());
+        fmt_chunk.extend_from_slice(&(channels).to_le_bytes()); // block_align
+        fmt_chunk.extend_from_slice(&8u16.to_le_bytes()); // bits_per_sample
+        fmt_chunk.extend_from_slice(&22u16.to_le_bytes()); // cbSize (extension size)
+        fmt_chunk.extend_from_slice(&8u16.to_le_bytes()); // valid bits per sample
+        fmt_chunk.extend_from_slice(&0u32.to_le_bytes()); // channel mask
+        fmt_chunk.extend_from_slice(&format_tag.to_le_bytes()); // sub-format GUID, first 2 bytes
+        fmt_chunk.extend_from_slice(&[
+            0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+        ]);
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // riff size, unused by our parser
+        bytes.extend_from_slice(b"WAVE");
+
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&(fmt_chunk.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&fmt_chunk);
+
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+
+        bytes
+    }
+
+    #[test]
+    fn decode_pcm_resolves_alaw_wrapped_in_waveformatextensible_header() {
+        let data = [0xD5, 0x55];
+        let plain = build_companded_wav(6, &data, 8_000, 1);
+        let extensible = build_extensible_wav(6, &data, 8_000, 1);
+
+        let plain_result = decode::decode_pcm(&plain).unwrap();
+        let extensible_result = decode::decode_pcm(&extensible).unwrap();
+        assert_eq!(plain_result, extensible_result);
+
+        let (duration, sample_rate) = decode::duration_and_sample_rate(&extensible);
+        assert_eq!(sample_rate, 8_000);
+        assert!((duration - 2.0 / 8_000.0).abs() < f64::EPSILON);
+    }
+
+    /// Builds a `bits_per_sample`/`sample_format` WAV fixture, writing
+    /// `samples` (already in `[-1.0, 1.0]`) scaled to that bit depth. Lets
+    /// the same helper exercise both the 8-bit unsigned and 32-bit float
+    /// paths through `hound`, rather than asserting on the `decode.rs`
+    /// scaling math in isolation.
+    fn build_pcm_wav(
+        bits_per_sample: u16,
+        sample_format: hound::SampleFormat,
+        samples: &[f32],
+        sample_rate: u32,
+        channels: u16,
+    ) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        };
+        let mut bytes = Vec::new();
+        {
+            let mut writer = hound::WavWriter::new(std::io::Cursor::new(&mut bytes), spec).unwrap();
+            match sample_format {
+                hound::SampleFormat::Float => {
+                    for &sample in samples {
+                        writer.write_sample(sample).unwrap();
+                    }
+                }
+                hound::SampleFormat::Int => {
+                    let max_value = (1i64 << (bits_per_sample - 1)) as f32;
+                    for &sample in samples {
+                        writer
+                            .write_sample((sample.clamp(-1.0, 1.0) * (max_value - 1.0)) as i32)
+                            .unwrap();
+                    }
+                }
+            }
+            writer.finalize().unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn decode_pcm_round_trips_8_bit_unsigned_pcm_wav() {
+        let samples = vec![-1.0_f32, -0.5, 0.0, 0.5, 1.0];
+        let bytes = build_pcm_wav(8, hound::SampleFormat::Int, &samples, 8_000, 1);
+
+        let (decoded, sr, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(sr, 8_000);
+        assert_eq!(channels, 1);
+        assert_eq!(decoded.len(), samples.len());
+        // 8-bit PCM only has 256 distinct levels, so allow a coarse
+        // tolerance for the quantization rather than asserting equality.
+        for (original, decoded) in samples.iter().zip(decoded.iter()) {
+            assert!(
+                (original - decoded).abs() < 0.02,
+                "original {original} decoded {decoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn decode_pcm_round_trips_32_bit_float_wav() {
+        let samples = vec![-1.0_f32, -0.25, 0.0, 0.25, 1.0];
+        let bytes = build_pcm_wav(32, hound::SampleFormat::Float, &samples, 16_000, 1);
+
+        let (decoded, sr, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(sr, 16_000);
+        assert_eq!(channels, 1);
+        assert_eq!(decoded, samples);
+    }
+
+    #[test]
+    fn measure_integrated_loudness_returns_finite_value_for_full_scale_tone() {
+        let sample_rate = 48_000u32;
+        let frame_count = sample_rate as usize;
+        let samples: Vec<f32> = (0..frame_count)
+            .map(|i| (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / sample_rate as f64).sin() as f32)
+            .collect();
+
+        let lufs = loudness::measure_integrated_loudness(&samples, 1, sample_rate);
+        assert!(lufs.is_finite());
+        assert!(lufs < 0.0);
+    }
+
+    #[test]
+    fn measure_integrated_loudness_gates_out_silence() {
+        let samples = vec![0.0f32; 48_000];
+        let lufs = loudness::measure_integrated_loudness(&samples, 1, 48_000);
+        assert_eq!(lufs, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn normalization_gain_and_apply_gain_match_target_amplitude() {
+        let gain = loudness::normalization_gain(-20.0, -14.0);
+        assert!((gain - 10f64.powf(6.0 / 20.0)).abs() < 1e-9);
+
+        let mut samples = vec![0.5f32, -0.5, 0.25];
+        loudness::apply_gain(&mut samples, 2.0);
+        assert_eq!(samples, vec![1.0, -1.0, 0.5]);
+    }
+
+    #[test]
+    fn shard_is_unchanged_requires_every_entry_to_hit_the_manifest() {
+        let mut manifest = manifest::Manifest::default();
+        manifest.set_config_digest("digest".to_string());
+        manifest.record(
+            "clip.wav".to_string(),
+            manifest::ManifestEntry {
+                size: 10,
+                mtime: 1,
+                duration: 1.0,
+                sampling_rate: 8_000,
+                digest: "abc".to_string(),
+            },
+        );
+
+        let all_cached = vec![("clip.wav".to_string(), Some((10, 1)))];
+        assert!(shard_is_unchanged(&manifest, "digest", &all_cached));
+
+        let stale_mtime = vec![("clip.wav".to_string(), Some((10, 2)))];
+        assert!(!shard_is_unchanged(&manifest, "digest", &stale_mtime));
+
+        let mixed_with_uncached = vec![
+            ("clip.wav".to_string(), Some((10, 1))),
+            ("other.wav".to_string(), Some((5, 1))),
+        ];
+        assert!(!shard_is_unchanged(&manifest, "digest", &mixed_with_uncached));
+
+        let has_non_local_entry = vec![("clip.wav".to_string(), Some((10, 1))), ("remote.wav".to_string(), None)];
+        assert!(!shard_is_unchanged(&manifest, "digest", &has_non_local_entry));
+
+        assert!(!shard_is_unchanged(&manifest, "digest", &[]));
+    }
+
+    #[test]
+    fn shard_is_unchanged_requires_the_config_digest_to_match() {
+        let mut manifest = manifest::Manifest::default();
+        manifest.set_config_digest("old-digest".to_string());
+        manifest.record(
+            "clip.wav".to_string(),
+            manifest::ManifestEntry {
+                size: 10,
+                mtime: 1,
+                duration: 1.0,
+                sampling_rate: 8_000,
+                digest: "abc".to_string(),
+            },
+        );
+
+        let all_cached = vec![("clip.wav".to_string(), Some((10, 1)))];
+        assert!(shard_is_unchanged(&manifest, "old-digest", &all_cached));
+        assert!(!shard_is_unchanged(&manifest, "new-digest", &all_cached));
+    }
+
+    #[test]
+    fn config_digest_changes_when_a_processing_flag_changes() {
+        let base = config_digest(&Args::parse_from(["audios-to-dataset"]));
+        let with_dedup = config_digest(&Args::parse_from(["audios-to-dataset", "--dedup"]));
+        let with_features = config_digest(&Args::parse_from([
+            "audios-to-dataset",
+            "--extract-features",
+        ]));
+
+        assert_ne!(base, with_dedup);
+        assert_ne!(base, with_features);
+        assert_ne!(with_dedup, with_features);
+        assert_eq!(base, config_digest(&Args::parse_from(["audios-to-dataset"])));
+    }
+
+    #[test]
+    fn config_digest_changes_when_the_duration_bounds_change() {
+        let base = config_digest(&Args::parse_from(["audios-to-dataset"]));
+        let with_min_duration = config_digest(&Args::parse_from([
+            "audios-to-dataset",
+            "--min-duration",
+            "1.0",
+        ]));
+        let with_max_duration = config_digest(&Args::parse_from([
+            "audios-to-dataset",
+            "--max-duration",
+            "10.0",
+        ]));
+
+        assert_ne!(base, with_min_duration);
+        assert_ne!(base, with_max_duration);
+        assert_ne!(with_min_duration, with_max_duration);
+    }
+
+    #[test]
+    fn load_chunk_store_recovers_digests_written_by_write_chunk_store() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let output_location = Location::Local(temp_dir.path().to_path_buf());
+        let runtime = storage::build_runtime()?;
+
+        let written = dedup::ChunkStore::new();
+        written.insert_if_absent("digest-a", b"hello");
+        written.insert_if_absent("digest-b", b"world");
+        write_chunk_store(&written, &output_location, runtime.handle())?;
+
+        let loaded = dedup::ChunkStore::new();
+        load_chunk_store(&loaded, &output_location)?;
+
+        let mut unique_chunks = loaded.unique_chunks();
+        unique_chunks.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            unique_chunks,
+            vec![
+                ("digest-a".to_string(), b"hello".to_vec()),
+                ("digest-b".to_string(), b"world".to_vec()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_chunk_store_is_a_no_op_when_no_dedup_chunks_file_exists() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let output_location = Location::Local(temp_dir.path().to_path_buf());
+
+        let loaded = dedup::ChunkStore::new();
+        load_chunk_store(&loaded, &output_location)?;
+
+        assert!(loaded.unique_chunks().is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,175 @@
+//! An on-disk cache of per-file decode probes, keyed by path and mirroring
+//! how a dirstate detects unchanged entries via path + truncated timestamp +
+//! size. For each processed file it records the relative path, size, a
+//! truncated mtime, and the computed duration/sampling_rate/content digest.
+//!
+//! `main` uses this two ways: a per-file one (skip only the
+//! `decode::duration_and_sample_rate` probe and the BLAKE3 digest for an
+//! unchanged file, still reading/transcoding/feature-extracting/writing it
+//! into whatever shard the current run assigns), and a per-shard one
+//! (`shard_is_unchanged`, `main.rs`): when every file a shard's chunk would
+//! contain is unchanged and the shard file from the last run still exists,
+//! that shard is left on disk untouched instead of being re-read and
+//! rewritten. The per-shard skip only fires for shards whose membership is
+//! stable across runs — chunking is positional over the current directory
+//! scan, so it works for the common case of a corpus that only grows by
+//! appending new files after the existing ones, but not if files are
+//! inserted, renamed, or removed in a way that shifts later files into
+//! different shard positions.
+//!
+//! The manifest also records a `config_digest` of the processing-relevant CLI
+//! flags (dedup, resampling, target codec, feature/loudness extraction, tag
+//! reading, partitioning, ...) from the run that last saved it. The per-shard
+//! skip additionally requires this to match the current run's digest via
+//! [`Manifest::config_matches`], since a cached shard built under different
+//! flags doesn't reflect what the current invocation was asked to produce.
+//!
+//! A skipped shard under `--dedup` leaves its chunks' digests referenced
+//! only by the untouched shard file, not by anything re-read this run, so
+//! `main.rs`'s `write_chunk_store` would otherwise drop them when it
+//! rewrites `dedup_chunks.parquet` from scratch; `load_chunk_store` seeds
+//! the in-memory chunk store from the existing file before the scan to keep
+//! those digests alive across the rewrite.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub size: u64,
+    pub mtime: u64,
+    pub duration: f64,
+    pub sampling_rate: i32,
+    pub digest: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    entries: HashMap<String, ManifestEntry>,
+    /// A digest of the processing-relevant CLI flags (dedup, resampling,
+    /// target codec, feature/loudness extraction, tag reading, partitioning,
+    /// ...) in effect the last time this manifest was saved. Per-file entries
+    /// stay valid across a flag change since they only cache the
+    /// config-independent duration/sample-rate probe, but the shard-level
+    /// skip in `shard_is_unchanged` (`main.rs`) must not fire when this
+    /// doesn't match the current run's digest, or a shard built under the old
+    /// flags would be left on disk as if the new ones had been applied.
+    config_digest: Option<String>,
+}
+
+impl Manifest {
+    /// Loads a manifest from `path`, falling back to an empty one if it
+    /// doesn't exist yet or fails to parse (e.g. the first run, or `--force`
+    /// isn't meant to require deleting it by hand).
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Returns the cached entry for `relative_path` when its `size`/`mtime`
+    /// still match what was recorded, meaning the file is unchanged since
+    /// the last run.
+    pub fn unchanged(&self, relative_path: &str, size: u64, mtime: u64) -> Option<&ManifestEntry> {
+        self.entries
+            .get(relative_path)
+            .filter(|entry| entry.size == size && entry.mtime == mtime)
+    }
+
+    pub fn record(&mut self, relative_path: String, entry: ManifestEntry) {
+        self.entries.insert(relative_path, entry);
+    }
+
+    /// Whether `digest` matches the config digest this manifest was last
+    /// saved with. A freshly loaded manifest with no recorded digest (e.g.
+    /// one written before this field existed) never matches, so a shard-level
+    /// skip can't be based on a config that was never actually recorded.
+    pub fn config_matches(&self, digest: &str) -> bool {
+        self.config_digest.as_deref() == Some(digest)
+    }
+
+    pub fn set_config_digest(&mut self, digest: String) {
+        self.config_digest = Some(digest);
+    }
+}
+
+/// A second-granularity mtime, truncated the same way a dirstate would to
+/// avoid false "changed" detections from sub-second filesystem precision
+/// differences.
+pub fn truncated_mtime(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_requires_matching_size_and_mtime() {
+        let mut manifest = Manifest::default();
+        manifest.record(
+            "clip.wav".to_string(),
+            ManifestEntry {
+                size: 100,
+                mtime: 42,
+                duration: 1.0,
+                sampling_rate: 16_000,
+                digest: "abc".to_string(),
+            },
+        );
+
+        assert!(manifest.unchanged("clip.wav", 100, 42).is_some());
+        assert!(manifest.unchanged("clip.wav", 101, 42).is_none());
+        assert!(manifest.unchanged("clip.wav", 100, 43).is_none());
+        assert!(manifest.unchanged("missing.wav", 100, 42).is_none());
+    }
+
+    #[test]
+    fn config_matches_requires_a_recorded_digest_equal_to_the_current_one() {
+        let mut manifest = Manifest::default();
+        assert!(!manifest.config_matches("abc"));
+
+        manifest.set_config_digest("abc".to_string());
+        assert!(manifest.config_matches("abc"));
+        assert!(!manifest.config_matches("def"));
+    }
+
+    #[test]
+    fn save_and_load_round_trips() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("manifest.json");
+
+        let mut manifest = Manifest::default();
+        manifest.record(
+            "clip.wav".to_string(),
+            ManifestEntry {
+                size: 10,
+                mtime: 1,
+                duration: 0.5,
+                sampling_rate: 8_000,
+                digest: "digest".to_string(),
+            },
+        );
+        manifest.save(&path)?;
+
+        let loaded = Manifest::load(&path);
+        assert!(loaded.unchanged("clip.wav", 10, 1).is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+//! Resampling, channel downmix, and re-encoding of decoded PCM audio, driven
+//! by `--target-sample-rate`/`--target-channels`/`--target-codec` so every
+//! stored clip can share one format for ML training instead of needing a
+//! separate ffmpeg preprocessing pass.
+
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow};
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::TargetCodec;
+use crate::decode;
+
+/// Downmixes interleaved `samples` (`channels` per frame) to mono by
+/// averaging each frame's channels.
+fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+
+    let channels = channels as usize;
+    samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Resamples interleaved `samples` from `src_rate` to `dst_rate` with a
+/// band-limited windowed-sinc (Lanczos) interpolator.
+fn resample_sinc(samples: &[f32], channels: u16, src_rate: u32, dst_rate: u32) -> Vec<f32> {
+    if src_rate == dst_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    const WINDOW: isize = 8;
+
+    let channels = channels.max(1) as usize;
+    let frame_count = samples.len() / channels;
+    let ratio = src_rate as f64 / dst_rate as f64;
+    let out_frames = (frame_count as f64 / ratio).round() as usize;
+    // When downsampling, stretch the kernel so its cutoff sits at the
+    // destination Nyquist frequency instead of the source one.
+    let scale = (1.0 / ratio).min(1.0);
+    let half_width = (WINDOW as f64 / scale).ceil() as isize;
+
+    let mut output = Vec::with_capacity(out_frames * channels);
+    for out_index in 0..out_frames {
+        let src_pos = out_index as f64 * ratio;
+        let src_center = src_pos.floor() as isize;
+
+        for channel in 0..channels {
+            let mut acc = 0.0f64;
+            for tap in -half_width..=half_width {
+                let src_index = src_center + tap;
+                if src_index < 0 || src_index as usize >= frame_count {
+                    continue;
+                }
+
+                let weight =
+                    scale * lanczos_kernel((src_pos - src_index as f64) * scale, WINDOW as f64);
+                acc += weight * samples[src_index as usize * channels + channel] as f64;
+            }
+            output.push(acc as f32);
+        }
+    }
+
+    output
+}
+
+fn lanczos_kernel(x: f64, a: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= a {
+        return 0.0;
+    }
+
+    let pi_x = std::f64::consts::PI * x;
+    (pi_x.sin() / pi_x) * ((pi_x / a).sin() / (pi_x / a))
+}
+
+fn encode_wav(samples: &[f32], channels: u16, sample_rate: u32) -> Result<Vec<u8>> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let mut bytes = Vec::new();
+    {
+        let mut writer = WavWriter::new(Cursor::new(&mut bytes), spec)?;
+        for sample in samples {
+            writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        }
+        writer.finalize()?;
+    }
+    Ok(bytes)
+}
+
+fn encode_mp3(samples: &[f32], channels: u16, sample_rate: u32) -> Result<Vec<u8>> {
+    use mp3lame_encoder::{Builder, DualPcm, FlushNoGap, MonoPcm};
+
+    if channels > 2 {
+        return Err(anyhow!(
+            "mp3 encoding supports at most 2 channels, got {channels}; downmix (e.g. --mono) before encoding"
+        ));
+    }
+
+    let mut builder = Builder::new().ok_or_else(|| anyhow!("failed to initialize mp3 encoder"))?;
+    builder
+        .set_num_channels(channels as u8)
+        .map_err(|err| anyhow!("unsupported mp3 channel count: {err:?}"))?;
+    builder
+        .set_sample_rate(sample_rate)
+        .map_err(|err| anyhow!("unsupported mp3 sample rate: {err:?}"))?;
+    builder
+        .set_quality(mp3lame_encoder::Quality::Good)
+        .map_err(|err| anyhow!("failed to set mp3 quality: {err:?}"))?;
+    let mut encoder = builder
+        .build()
+        .map_err(|err| anyhow!("failed to build mp3 encoder: {err:?}"))?;
+
+    let pcm_i16: Vec<i16> = samples
+        .iter()
+        .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect();
+
+    let mut out = Vec::new();
+    out.reserve(mp3lame_encoder::max_required_buffer_size(pcm_i16.len()));
+
+    if channels <= 1 {
+        let input = MonoPcm(&pcm_i16);
+        encoder
+            .encode_to_vec(input, &mut out)
+            .map_err(|err| anyhow!("mp3 encoding failed: {err:?}"))?;
+    } else {
+        let (left, right): (Vec<i16>, Vec<i16>) = pcm_i16
+            .chunks(2)
+            .map(|frame| (frame[0], *frame.get(1).unwrap_or(&frame[0])))
+            .unzip();
+        let input = DualPcm {
+            left: &left,
+            right: &right,
+        };
+        encoder
+            .encode_to_vec(input, &mut out)
+            .map_err(|err| anyhow!("mp3 encoding failed: {err:?}"))?;
+    }
+
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut out)
+        .map_err(|err| anyhow!("mp3 flush failed: {err:?}"))?;
+
+    Ok(out)
+}
+
+fn encode_flac(samples: &[f32], channels: u16, sample_rate: u32) -> Result<Vec<u8>> {
+    let pcm: Vec<i32> = samples
+        .iter()
+        .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i32)
+        .collect();
+
+    let config = flacenc::config::Encoder::default();
+    let source =
+        flacenc::source::MemSource::from_samples(&pcm, channels as usize, 16, sample_rate as usize);
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|err| anyhow!("flac encoding failed: {err:?}"))?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|err| anyhow!("flac bitstream write failed: {err:?}"))?;
+    Ok(sink.into_inner())
+}
+
+/// Resamples/downmixes/re-encodes `bytes` to the requested target
+/// representation, returning the new bytes and the resulting sample rate.
+/// When none of `target_sample_rate`/`target_channels`/`target_codec` call
+/// for a change, `bytes` is returned untouched. `TargetCodec::PassThrough`
+/// keeps the source encoding unless resampling/downmixing forced a decode, in
+/// which case it falls back to PCM WAV.
+pub fn transcode(
+    bytes: &[u8],
+    source_sample_rate: i32,
+    target_sample_rate: Option<u32>,
+    target_channels: Option<u16>,
+    target_codec: TargetCodec,
+) -> Result<(Vec<u8>, i32)> {
+    let needs_resample = target_sample_rate.is_some_and(|rate| rate as i32 != source_sample_rate);
+    let needs_downmix = target_channels == Some(1);
+
+    if !needs_resample && !needs_downmix && target_codec == TargetCodec::PassThrough {
+        return Ok((bytes.to_vec(), source_sample_rate));
+    }
+
+    let (mut samples, src_rate, mut channels) = decode::decode_pcm(bytes)?;
+
+    if needs_downmix {
+        samples = downmix_to_mono(&samples, channels);
+        channels = 1;
+    }
+
+    let dst_rate = target_sample_rate.unwrap_or(src_rate);
+    if dst_rate != src_rate {
+        samples = resample_sinc(&samples, channels, src_rate, dst_rate);
+    }
+
+    let encoded = encode_pcm(&samples, channels, dst_rate, target_codec)?;
+
+    Ok((encoded, dst_rate as i32))
+}
+
+/// Encodes already-resampled/downmixed PCM `samples` to `target_codec`,
+/// the shared tail end of [`transcode`] also used by loudness normalization
+/// to re-encode gain-adjusted samples without repeating the codec match.
+pub fn encode_pcm(
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    target_codec: TargetCodec,
+) -> Result<Vec<u8>> {
+    match target_codec {
+        TargetCodec::PassThrough | TargetCodec::Wav => encode_wav(samples, channels, sample_rate),
+        TargetCodec::Flac => encode_flac(samples, channels, sample_rate),
+        TargetCodec::Mp3 => encode_mp3(samples, channels, sample_rate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine_tone(frequency: f64, sample_rate: u32, seconds: f64) -> Vec<f32> {
+        let frame_count = (sample_rate as f64 * seconds) as usize;
+        (0..frame_count)
+            .map(|i| (2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64).sin() as f32)
+            .collect()
+    }
+
+    /// A 440 Hz tone resampled from 16 kHz to 8 kHz should keep roughly
+    /// half the frame count and the same number of zero crossings per
+    /// second, i.e. the same perceived pitch.
+    #[test]
+    fn resample_sinc_preserves_duration_and_pitch() {
+        let src_rate = 16_000;
+        let dst_rate = 8_000;
+        let frequency = 440.0;
+        let samples = sine_tone(frequency, src_rate, 1.0);
+
+        let resampled = resample_sinc(&samples, 1, src_rate, dst_rate);
+
+        let expected_frames = (samples.len() as f64 * dst_rate as f64 / src_rate as f64).round() as usize;
+        assert!((resampled.len() as isize - expected_frames as isize).abs() <= 1);
+
+        let crossings = resampled
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        let estimated_frequency = crossings as f64 / 2.0 / (resampled.len() as f64 / dst_rate as f64);
+        assert!((estimated_frequency - frequency).abs() < 10.0);
+    }
+
+    #[test]
+    fn downmix_to_mono_averages_channels() {
+        let stereo = vec![1.0, -1.0, 0.5, 0.5, 0.0, 1.0];
+        let mono = downmix_to_mono(&stereo, 2);
+        assert_eq!(mono, vec![0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn encode_wav_round_trips_through_decode_pcm() {
+        let samples = sine_tone(440.0, 8_000, 0.1);
+        let bytes = encode_wav(&samples, 1, 8_000).unwrap();
+
+        let (decoded, sample_rate, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(sample_rate, 8_000);
+        assert_eq!(channels, 1);
+        assert_eq!(decoded.len(), samples.len());
+        for (original, decoded) in samples.iter().zip(decoded.iter()) {
+            assert!((original - decoded).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn encode_flac_round_trips_through_decode_pcm() {
+        let samples = sine_tone(440.0, 8_000, 0.1);
+        let bytes = encode_flac(&samples, 1, 8_000).unwrap();
+
+        let (decoded, sample_rate, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(sample_rate, 8_000);
+        assert_eq!(channels, 1);
+        assert_eq!(decoded.len(), samples.len());
+        for (original, decoded) in samples.iter().zip(decoded.iter()) {
+            assert!((original - decoded).abs() < 0.01);
+        }
+    }
+
+    /// MP3 is lossy and block-based, so this only checks that the encoder
+    /// produces a decodable stream whose duration and pitch roughly survive
+    /// the round trip, not sample-accurate reconstruction.
+    #[test]
+    fn encode_mp3_round_trips_with_preserved_duration_and_pitch() {
+        let frequency = 440.0;
+        let sample_rate = 44_100;
+        let samples = sine_tone(frequency, sample_rate, 0.5);
+        let bytes = encode_mp3(&samples, 1, sample_rate).unwrap();
+
+        let (decoded, decoded_rate, channels) = decode::decode_pcm(&bytes).unwrap();
+        assert_eq!(decoded_rate, sample_rate);
+        assert_eq!(channels, 1);
+
+        let decoded_seconds = decoded.len() as f64 / decoded_rate as f64;
+        assert!((decoded_seconds - 0.5).abs() < 0.1);
+
+        let crossings = decoded
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        let estimated_frequency = crossings as f64 / 2.0 / decoded_seconds;
+        assert!((estimated_frequency - frequency).abs() < 20.0);
+    }
+
+    /// `DualPcm` only carries left/right slices, so a >2-channel source must
+    /// be rejected rather than silently folded into a stereo pair via naive
+    /// `chunks(2)` interleaving (which would drop channels 3+ and scramble
+    /// the remaining ones).
+    #[test]
+    fn encode_mp3_rejects_more_than_two_channels() {
+        let samples = sine_tone(440.0, 44_100, 0.1);
+        let four_channel: Vec<f32> = samples
+            .iter()
+            .flat_map(|&sample| [sample, sample, sample, sample])
+            .collect();
+
+        let err = encode_mp3(&four_channel, 4, 44_100).unwrap_err();
+        assert!(err.to_string().contains("at most 2 channels"));
+    }
+}